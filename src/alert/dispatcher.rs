@@ -0,0 +1,194 @@
+use crate::alert::email::EmailNotifier;
+use crate::alert::pagerduty::PagerDutyNotifier;
+use crate::alert::routing::Router;
+use crate::alert::slack::SlackNotifier;
+use crate::alert::webhook::WebhookNotifier;
+use crate::alert::{AlertEvent, AlertKind};
+use crate::config::{Config, NotifierConfig};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Delivery outcomes kept for the API.
+const MAX_DELIVERIES: usize = 500;
+
+enum Notifier {
+    Email(EmailNotifier),
+    Webhook(WebhookNotifier),
+    Slack(SlackNotifier),
+    PagerDuty(PagerDutyNotifier),
+}
+
+impl Notifier {
+    async fn send(&self, event: &AlertEvent) -> anyhow::Result<()> {
+        match self {
+            Notifier::Email(notifier) => notifier.send(event).await,
+            Notifier::Webhook(notifier) => notifier.send(event).await,
+            Notifier::Slack(notifier) => notifier.send(event).await,
+            Notifier::PagerDuty(notifier) => notifier.send(event).await,
+        }
+    }
+}
+
+/// Delivers alert events to the notifiers chosen by the [`Router`].
+///
+/// Notifiers are built at startup and rebuilt when the config is reloaded.
+/// Each notifier has its own queue, worked off by a task one alert at a time,
+/// so a slow or failing notifier never holds up the check loop or the others,
+/// and alerts reach it in the order they were raised; a PagerDuty resolve
+/// never overtakes its trigger. The outcome of recent deliveries is kept for
+/// the API.
+pub struct Dispatcher {
+    notifiers: HashMap<String, Arc<Notifier>>,
+    router: Router,
+    /// Queue of each notifier name, kept across reloads.
+    queues: Arc<Mutex<HashMap<String, Queue>>>,
+    deliveries: Arc<Mutex<VecDeque<Delivery>>>,
+}
+
+/// Alerts waiting for one notifier, with the notifier they were raised for.
+type Queue = mpsc::UnboundedSender<(Arc<Notifier>, Arc<AlertEvent>)>;
+
+/// Result of sending one alert through one notifier.
+#[derive(Clone, Serialize)]
+pub struct Delivery {
+    pub timestamp: DateTime<Utc>,
+    pub notifier: String,
+    pub service: String,
+    pub kind: AlertKind,
+    pub incident_id: u64,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl Dispatcher {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut notifiers = HashMap::new();
+        for (name, notifier) in &config.notifiers {
+            let notifier = match notifier {
+                NotifierConfig::Email(email) => Notifier::Email(EmailNotifier::new(email)?),
+                NotifierConfig::Webhook(webhook) => {
+                    Notifier::Webhook(WebhookNotifier::new(webhook)?)
+                }
+                NotifierConfig::Slack(slack) => Notifier::Slack(SlackNotifier::new(slack)?),
+                NotifierConfig::PagerDuty(pagerduty) => {
+                    Notifier::PagerDuty(PagerDutyNotifier::new(pagerduty)?)
+                }
+            };
+            notifiers.insert(name.clone(), Arc::new(notifier));
+        }
+
+        Ok(Self {
+            notifiers,
+            router: Router::new(config),
+            queues: Arc::new(Mutex::new(HashMap::new())),
+            deliveries: Arc::new(Mutex::new(VecDeque::new())),
+        })
+    }
+
+    /// Notifiers and rules of a reloaded `config`, keeping the log of
+    /// recent deliveries and the queues of notifiers that still exist, so
+    /// alerts raised before the reload are delivered first.
+    pub fn reload(&self, config: &Config) -> anyhow::Result<Self> {
+        let dispatcher = Self {
+            queues: self.queues.clone(),
+            deliveries: self.deliveries.clone(),
+            ..Self::new(config)?
+        };
+        // Dropping a queue lets its task finish what is queued and exit.
+        self.queues
+            .lock()
+            .unwrap()
+            .retain(|name, _| config.notifiers.contains_key(name));
+        Ok(dispatcher)
+    }
+
+    /// Sends `event` and returns the notifiers it went to.
+    ///
+    /// A recovery goes to `notified`, the notifiers that received the down
+    /// alert, even if the rules would route it elsewhere by now.
+    pub fn dispatch(&self, event: AlertEvent, notified: &[String]) -> Vec<String> {
+        let names = match event.kind {
+            AlertKind::Recovered if !notified.is_empty() => notified.to_vec(),
+            _ => self.router.route(&event, Utc::now()),
+        };
+        self.send_to(event, &names);
+        names
+    }
+
+    /// Sends `event` to the notifiers in `names`, bypassing the rules.
+    pub fn send_to(&self, event: AlertEvent, names: &[String]) {
+        let event = Arc::new(event);
+        let mut queues = self.queues.lock().unwrap();
+        for name in names {
+            let Some(notifier) = self.notifiers.get(name).cloned() else {
+                continue;
+            };
+            let queue = queues
+                .entry(name.clone())
+                .or_insert_with(|| start_queue(name.clone(), self.deliveries.clone()));
+            if queue.send((notifier, event.clone())).is_err() {
+                println!(
+                    "Alert queue of {} stopped, dropping {} alert for {}",
+                    name,
+                    event.kind.label(),
+                    event.service
+                );
+            }
+        }
+    }
+
+    /// Recent deliveries, newest first.
+    pub fn deliveries(&self) -> Vec<Delivery> {
+        self.deliveries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Starts the task that delivers the alerts queued for notifier `name`, one
+/// at a time including retries.
+fn start_queue(name: String, deliveries: Arc<Mutex<VecDeque<Delivery>>>) -> Queue {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(Arc<Notifier>, Arc<AlertEvent>)>();
+    tokio::spawn(async move {
+        while let Some((notifier, event)) = receiver.recv().await {
+            let result = notifier.send(&event).await;
+            match &result {
+                Ok(()) => println!(
+                    "Sent {} alert for {} via {}",
+                    event.kind.label(),
+                    event.service,
+                    name
+                ),
+                Err(e) => println!(
+                    "Failed to send {} alert for {} via {}: {:#}",
+                    event.kind.label(),
+                    event.service,
+                    name,
+                    e
+                ),
+            }
+
+            let mut deliveries = deliveries.lock().unwrap();
+            deliveries.push_back(Delivery {
+                timestamp: Utc::now(),
+                notifier: name.clone(),
+                service: event.service.clone(),
+                kind: event.kind,
+                incident_id: event.incident_id,
+                success: result.is_ok(),
+                error: result.err().map(|e| format!("{:#}", e)),
+            });
+            while deliveries.len() > MAX_DELIVERIES {
+                deliveries.pop_front();
+            }
+        }
+    });
+    sender
+}
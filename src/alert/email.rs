@@ -1 +1,313 @@
-/* TODO */
+use crate::alert::template::{render, render_html};
+use crate::alert::{AlertEvent, AlertKind};
+use crate::config::{EmailConfig, SmtpTls};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+const DEFAULT_SUBJECT: &str = "[{{status}}] {{service}}";
+
+const DOWN_TEXT: &str = "{{service}} is DOWN.
+
+URL: {{url}}
+Failing checks: {{checks}}
+Since: {{started_at}}
+
+{{message}}
+
+Incident #{{incident_id}}
+";
+
+const ESCALATED_TEXT: &str = "{{service}} is still DOWN and has not been acknowledged.
+
+This is escalation level {{escalation_level}}.
+
+URL: {{url}}
+Failing checks: {{checks}}
+Since: {{started_at}}
+
+{{message}}
+
+Incident #{{incident_id}}
+";
+
+const FLAPPING_TEXT: &str = "{{service}} is FLAPPING between up and down.
+
+Further state changes are not alerted until it settles.
+
+URL: {{url}}
+Checks: {{checks}}
+
+{{message}}
+
+Incident #{{incident_id}}
+";
+
+const DEGRADED_TEXT: &str = "{{service}} is DEGRADED: still working, but slow or about to fail.
+
+URL: {{url}}
+Checks: {{checks}}
+
+{{message}}
+
+Incident #{{incident_id}}
+";
+
+const RECOVERED_TEXT: &str = "{{service}} has RECOVERED after {{duration}}.
+
+URL: {{url}}
+Failing checks: {{checks}}
+Down since: {{started_at}}
+Recovered at: {{timestamp}}
+
+Incident #{{incident_id}}
+";
+
+const DOWN_HTML: &str = r#"<h2 style="color: #991b1b;">{{service}} is DOWN</h2>
+<p><a href="{{url}}">{{url}}</a></p>
+<table>
+<tr><td><b>Failing checks</b></td><td>{{checks}}</td></tr>
+<tr><td><b>Since</b></td><td>{{started_at}}</td></tr>
+</table>
+<pre>{{message}}</pre>
+<p style="color: #6b7280;">Incident #{{incident_id}}</p>
+"#;
+
+const ESCALATED_HTML: &str = r#"<h2 style="color: #991b1b;">{{service}} is still DOWN</h2>
+<p>Not acknowledged &mdash; escalation level {{escalation_level}}.</p>
+<p><a href="{{url}}">{{url}}</a></p>
+<table>
+<tr><td><b>Failing checks</b></td><td>{{checks}}</td></tr>
+<tr><td><b>Since</b></td><td>{{started_at}}</td></tr>
+</table>
+<pre>{{message}}</pre>
+<p style="color: #6b7280;">Incident #{{incident_id}}</p>
+"#;
+
+const FLAPPING_HTML: &str = r#"<h2 style="color: #92400e;">{{service}} is FLAPPING</h2>
+<p>Further state changes are not alerted until it settles.</p>
+<p><a href="{{url}}">{{url}}</a></p>
+<table>
+<tr><td><b>Checks</b></td><td>{{checks}}</td></tr>
+</table>
+<pre>{{message}}</pre>
+<p style="color: #6b7280;">Incident #{{incident_id}}</p>
+"#;
+
+const DEGRADED_HTML: &str = r#"<h2 style="color: #a16207;">{{service}} is DEGRADED</h2>
+<p>Still working, but slow or about to fail.</p>
+<p><a href="{{url}}">{{url}}</a></p>
+<table>
+<tr><td><b>Checks</b></td><td>{{checks}}</td></tr>
+</table>
+<pre>{{message}}</pre>
+<p style="color: #6b7280;">Incident #{{incident_id}}</p>
+"#;
+
+const RECOVERED_HTML: &str = r#"<h2 style="color: #065f46;">{{service}} has RECOVERED</h2>
+<p><a href="{{url}}">{{url}}</a></p>
+<table>
+<tr><td><b>Downtime</b></td><td>{{duration}}</td></tr>
+<tr><td><b>Failing checks</b></td><td>{{checks}}</td></tr>
+<tr><td><b>Down since</b></td><td>{{started_at}}</td></tr>
+<tr><td><b>Recovered at</b></td><td>{{timestamp}}</td></tr>
+</table>
+<p style="color: #6b7280;">Incident #{{incident_id}}</p>
+"#;
+
+/// Sends down and recovery alerts as multipart (plain text + HTML) emails.
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    subject: Option<String>,
+    text_body: Option<String>,
+    html_body: Option<String>,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &EmailConfig) -> anyhow::Result<Self> {
+        let mut builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        let transport = builder
+            .timeout(Some(Duration::from_millis(config.timeout_ms)))
+            .build();
+
+        let to = config
+            .to
+            .iter()
+            .map(|to| to.parse())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            transport,
+            from: config.from.parse()?,
+            to,
+            subject: config.subject.clone(),
+            text_body: config.text_body.clone(),
+            html_body: config.html_body.clone(),
+        })
+    }
+
+    pub async fn send(&self, event: &AlertEvent) -> anyhow::Result<()> {
+        let (text, html) = match event.kind {
+            AlertKind::Down => (DOWN_TEXT, DOWN_HTML),
+            AlertKind::Escalated => (ESCALATED_TEXT, ESCALATED_HTML),
+            AlertKind::Flapping => (FLAPPING_TEXT, FLAPPING_HTML),
+            AlertKind::Degraded => (DEGRADED_TEXT, DEGRADED_HTML),
+            AlertKind::Recovered => (RECOVERED_TEXT, RECOVERED_HTML),
+        };
+        let subject = render(self.subject.as_deref().unwrap_or(DEFAULT_SUBJECT), event);
+        let text = render(self.text_body.as_deref().unwrap_or(text), event);
+        let html = render_html(self.html_body.as_deref().unwrap_or(html), event);
+
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.multipart(MultiPart::alternative_plain_html(text, html))?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::incident::Incident;
+    use chrono::Utc;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// A mail accepted by the sink: its recipients and raw data.
+    struct ReceivedMail {
+        recipients: Vec<String>,
+        data: String,
+    }
+
+    /// Minimal plain SMTP server on a local port that accepts every mail.
+    async fn start_smtp_sink() -> (u16, mpsc::UnboundedReceiver<ReceivedMail>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    writer.write_all(b"220 sink ready\r\n").await?;
+
+                    let mut recipients = Vec::new();
+                    while let Some(line) = lines.next_line().await? {
+                        let command = line.to_ascii_uppercase();
+                        let reply: &[u8] = if command.starts_with("RCPT TO:") {
+                            recipients.push(line[8..].trim().to_string());
+                            b"250 OK\r\n"
+                        } else if command == "DATA" {
+                            writer.write_all(b"354 go ahead\r\n").await?;
+                            let mut data = String::new();
+                            while let Some(line) = lines.next_line().await? {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            let _ = sender.send(ReceivedMail {
+                                recipients: std::mem::take(&mut recipients),
+                                data,
+                            });
+                            b"250 queued\r\n"
+                        } else if command == "QUIT" {
+                            writer.write_all(b"221 bye\r\n").await?;
+                            break;
+                        } else {
+                            b"250 OK\r\n"
+                        };
+                        writer.write_all(reply).await?;
+                    }
+                    std::io::Result::Ok(())
+                });
+            }
+        });
+
+        (port, receiver)
+    }
+
+    #[tokio::test]
+    async fn sends_down_and_recovery_mail() {
+        let (port, mut received) = start_smtp_sink().await;
+        let config: EmailConfig = serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "port": port,
+            "tls": "None",
+            "from": "Monitor <monitor@example.com>",
+            "to": ["ops@example.com", "oncall@example.com"],
+            "subject": "[{{status}}] {{service}} #{{incident_id}}",
+        }))
+        .unwrap();
+        let notifier = EmailNotifier::new(&config).unwrap();
+
+        let now = Utc::now();
+        let mut incident = Incident::open(3, "Billing".to_string(), now, "HTTP 502".to_string());
+        incident.record_failure(now, "HTTP", "HTTP 502");
+        let down =
+            AlertEvent::from_incident(&incident, "https://billing.example.com", "HTTP", None);
+        incident.close(now + chrono::Duration::minutes(2));
+        let recovered =
+            AlertEvent::from_incident(&incident, "https://billing.example.com", "HTTP", None);
+
+        notifier.send(&down).await.unwrap();
+        notifier.send(&recovered).await.unwrap();
+
+        let mail = received.recv().await.unwrap();
+        assert_eq!(
+            mail.recipients,
+            ["<ops@example.com>", "<oncall@example.com>"]
+        );
+        assert!(mail.data.contains("Subject: [DOWN] Billing #3"));
+        let (text, html) = parts(&mail.data);
+        assert!(text.contains("Billing is DOWN."), "{}", text);
+        assert!(text.contains("HTTP 502"));
+        assert!(html.contains("Billing is DOWN</h2>"), "{}", html);
+        assert!(html.contains("<pre>HTTP 502</pre>"));
+
+        let mail = received.recv().await.unwrap();
+        assert!(mail.data.contains("Subject: [RECOVERED] Billing #3"));
+        let (text, html) = parts(&mail.data);
+        assert!(text.contains("Billing has RECOVERED after 2m"), "{}", text);
+        assert!(text.contains("Incident #3"));
+        assert!(html.contains("Billing has RECOVERED</h2>"), "{}", html);
+        assert!(html.contains("<td>HTTP</td>"));
+    }
+
+    /// Plain text and HTML part of a `multipart/alternative` mail.
+    fn parts(data: &str) -> (String, String) {
+        let part = |content_type: &str| {
+            let start = data.find(content_type).expect("part present");
+            let body = &data[start..];
+            let body = &body[body.find("\n\n").expect("part body") + 2..];
+            body[..body.find("\n--").expect("part end")].to_string()
+        };
+        (
+            part("Content-Type: text/plain"),
+            part("Content-Type: text/html"),
+        )
+    }
+}
@@ -0,0 +1,15 @@
+use crate::state::AppState;
+use chrono::Utc;
+use std::time::Duration;
+
+/// How often open incidents are checked against their escalation policy.
+const ESCALATION_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Escalates open, unacknowledged incidents as their policy tiers come due.
+pub async fn start_escalations(state: AppState) {
+    let mut interval = tokio::time::interval(ESCALATION_INTERVAL);
+    loop {
+        interval.tick().await;
+        state.escalate_incidents(Utc::now()).await;
+    }
+}
@@ -1,3 +1,100 @@
+use crate::models::incident::Incident;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub mod dispatcher;
 pub mod email;
+pub mod escalation;
+pub mod pagerduty;
+pub mod retry;
+pub mod routing;
 pub mod slack;
+pub mod template;
 pub mod webhook;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AlertKind {
+    Down,
+    /// Still down and unacknowledged after an escalation tier's delay.
+    Escalated,
+    /// Changing state too often; sent once until it settles.
+    Flapping,
+    /// Working but slow or close to failing, e.g. a certificate expiring soon.
+    Degraded,
+    Recovered,
+}
+
+impl AlertKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlertKind::Down => "DOWN",
+            AlertKind::Escalated => "ESCALATED",
+            AlertKind::Flapping => "FLAPPING",
+            AlertKind::Degraded => "DEGRADED",
+            AlertKind::Recovered => "RECOVERED",
+        }
+    }
+}
+
+/// A service going down, degrading or recovering, as sent to notifiers.
+#[derive(Clone, Debug, Serialize)]
+pub struct AlertEvent {
+    pub kind: AlertKind,
+    pub service: String,
+    pub url: String,
+    /// Check whose result caused the transition.
+    pub check_type: String,
+    pub response_time_ms: Option<u64>,
+    pub message: String,
+    pub failing_checks: Vec<String>,
+    pub incident_id: u64,
+    pub started_at: DateTime<Utc>,
+    pub duration_seconds: Option<i64>,
+    /// Escalation tier reached, 0 before any escalation.
+    pub escalation_level: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl AlertEvent {
+    /// Alert for `incident` having just opened or closed after a result of
+    /// `check_type`.
+    pub fn from_incident(
+        incident: &Incident,
+        url: &str,
+        check_type: &str,
+        response_time_ms: Option<u64>,
+    ) -> Self {
+        let kind = if !incident.is_open() {
+            AlertKind::Recovered
+        } else if incident.degraded {
+            AlertKind::Degraded
+        } else {
+            AlertKind::Down
+        };
+
+        Self {
+            kind,
+            service: incident.service_name.clone(),
+            url: url.to_string(),
+            check_type: check_type.to_string(),
+            response_time_ms,
+            message: incident.message.clone(),
+            failing_checks: incident.failing_checks.clone(),
+            incident_id: incident.id,
+            started_at: incident.started_at,
+            duration_seconds: incident.duration_seconds,
+            escalation_level: incident.escalation_level,
+            timestamp: incident.ended_at.unwrap_or(incident.started_at),
+        }
+    }
+
+    /// Alert for `incident` reaching escalation tier `incident.escalation_level`.
+    pub fn escalation(incident: &Incident, url: &str, now: DateTime<Utc>) -> Self {
+        let check_type = incident.failing_checks.first().cloned().unwrap_or_default();
+        Self {
+            kind: AlertKind::Escalated,
+            timestamp: now,
+            ..Self::from_incident(incident, url, &check_type, None)
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::alert::retry::send_with_retries;
+use crate::alert::{AlertEvent, AlertKind};
+use crate::config::{PagerDutyConfig, PagerDutySeverity};
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Mirrors incidents in PagerDuty through the Events API v2.
+///
+/// An opened incident sends `trigger` and its recovery sends `resolve`, both
+/// with the same `dedup_key`. The key is built from the service and the check
+/// that opened the incident, so a trigger repeated before a lost resolve
+/// updates the existing PagerDuty incident instead of creating a second one.
+/// Degraded services trigger with `degraded_severity`; if they go down, the
+/// trigger is repeated with `severity`.
+pub struct PagerDutyNotifier {
+    client: Client,
+    config: PagerDutyConfig,
+}
+
+impl PagerDutyNotifier {
+    pub fn new(config: &PagerDutyConfig) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+        })
+    }
+
+    pub async fn send(&self, event: &AlertEvent) -> anyhow::Result<()> {
+        let body = self.payload(event).to_string();
+        send_with_retries(
+            "PagerDuty",
+            self.config.retries,
+            Duration::from_millis(self.config.retry_delay_ms),
+            || {
+                self.client
+                    .post(&self.config.events_url)
+                    .header("Content-Type", "application/json")
+                    .body(body.clone())
+            },
+        )
+        .await
+    }
+
+    fn dedup_key(&self, event: &AlertEvent) -> String {
+        let check = event.failing_checks.first().unwrap_or(&event.check_type);
+        format!(
+            "{}/{}/{}",
+            self.config.dedup_key_prefix, event.service, check
+        )
+    }
+
+    fn payload(&self, event: &AlertEvent) -> Value {
+        let dedup_key = self.dedup_key(event);
+
+        if event.kind == AlertKind::Recovered {
+            return json!({
+                "routing_key": self.config.routing_key,
+                "event_action": "resolve",
+                "dedup_key": dedup_key,
+            });
+        }
+
+        let severity = match event.kind {
+            AlertKind::Degraded => self.config.degraded_severity,
+            _ => self.config.severity,
+        };
+        let severity = match severity {
+            PagerDutySeverity::Critical => "critical",
+            PagerDutySeverity::Error => "error",
+            PagerDutySeverity::Warning => "warning",
+            PagerDutySeverity::Info => "info",
+        };
+
+        let state = match event.kind {
+            AlertKind::Flapping => "flapping",
+            AlertKind::Degraded => "degraded",
+            _ => "down",
+        };
+
+        let mut links = Vec::new();
+        if event.url.starts_with("http://") || event.url.starts_with("https://") {
+            links.push(json!({ "href": event.url, "text": "Service" }));
+        }
+        if let Some(dashboard) = &self.config.dashboard_url {
+            let dashboard = dashboard.trim_end_matches('/');
+            if let Ok(incidents) = Url::parse_with_params(
+                &format!("{}/api/incidents", dashboard),
+                [("service", &event.service)],
+            ) {
+                links.push(json!({ "href": String::from(incidents), "text": "Incident details" }));
+            }
+        }
+
+        let mut body = json!({
+            "routing_key": self.config.routing_key,
+            "event_action": "trigger",
+            "dedup_key": dedup_key,
+            "payload": {
+                "summary": truncate(&format!("{} is {}: {}", event.service, state, event.message), 1024),
+                "source": event.url,
+                "severity": severity,
+                "timestamp": event.started_at.to_rfc3339(),
+                "component": event.service,
+                "class": event.check_type,
+                "custom_details": {
+                    "failing_checks": event.failing_checks,
+                    "message": event.message,
+                    "response_time_ms": event.response_time_ms,
+                    "incident_id": event.incident_id,
+                },
+            },
+            "client": "Service Health Monitor",
+            "links": links,
+        });
+        if let Some(dashboard) = &self.config.dashboard_url {
+            body["client_url"] = json!(dashboard);
+        }
+        body
+    }
+}
+
+/// PagerDuty rejects summaries longer than 1024 characters.
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::incident::Incident;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use chrono::Utc;
+    use tokio::sync::mpsc;
+
+    /// Local stand-in for the Events API that forwards every event it gets.
+    async fn start_events_api() -> (String, mpsc::UnboundedReceiver<Value>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let app = Router::new()
+            .route(
+                "/v2/enqueue",
+                post(
+                    |State(sender): State<mpsc::UnboundedSender<Value>>,
+                     Json(event): Json<Value>| async move {
+                        let _ = sender.send(event);
+                        StatusCode::ACCEPTED
+                    },
+                ),
+            )
+            .with_state(sender);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v2/enqueue", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn trigger_and_resolve_share_a_dedup_key() {
+        let (events_url, mut received) = start_events_api().await;
+        let config: PagerDutyConfig = serde_json::from_value(json!({
+            "routing_key": "test-key",
+            "events_url": events_url,
+            "retries": 0,
+        }))
+        .unwrap();
+        let notifier = PagerDutyNotifier::new(&config).unwrap();
+
+        let now = Utc::now();
+        let mut incident = Incident::open(7, "API".to_string(), now, "HTTP 503".to_string());
+        incident.record_failure(now, "HTTP", "HTTP 503");
+        let down =
+            AlertEvent::from_incident(&incident, "https://api.example.com", "HTTP", Some(40));
+        incident.close(now + chrono::Duration::minutes(5));
+        let recovered =
+            AlertEvent::from_incident(&incident, "https://api.example.com", "HTTP", Some(35));
+
+        notifier.send(&down).await.unwrap();
+        notifier.send(&recovered).await.unwrap();
+
+        let trigger = received.recv().await.unwrap();
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(trigger["routing_key"], "test-key");
+        assert_eq!(trigger["dedup_key"], "service-health-monitor/API/HTTP");
+        assert_eq!(trigger["payload"]["summary"], "API is down: HTTP 503");
+        assert_eq!(trigger["payload"]["source"], "https://api.example.com");
+        assert_eq!(trigger["payload"]["severity"], "critical");
+        assert_eq!(trigger["payload"]["custom_details"]["incident_id"], 7);
+
+        let resolve = received.recv().await.unwrap();
+        assert_eq!(resolve["event_action"], "resolve");
+        assert_eq!(resolve["dedup_key"], trigger["dedup_key"]);
+        assert!(resolve.get("payload").is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use reqwest::{RequestBuilder, StatusCode};
+use std::time::Duration;
+
+/// Longest pause between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Sends the request built by `request` until it succeeds, retrying up to
+/// `retries` times on network errors and on 5xx / 429 responses.
+///
+/// The pause starts at `delay` and doubles after every attempt; a
+/// `Retry-After` header (in seconds) on a 429 extends it. Other 4xx responses
+/// fail immediately.
+pub async fn send_with_retries(
+    name: &str,
+    retries: u32,
+    delay: Duration,
+    request: impl Fn() -> RequestBuilder,
+) -> anyhow::Result<()> {
+    let mut delay = delay;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let (error, retry_after) = match request().send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let retry_after = response
+                    .headers()
+                    .get("Retry-After")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse().ok())
+                    .map(Duration::from_secs);
+                let body = response.text().await.unwrap_or_default();
+                let error = anyhow::anyhow!("{} returned {}: {}", name, status, body.trim());
+                if !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                    return Err(error);
+                }
+                (error, retry_after)
+            }
+            Err(e) => (anyhow::Error::from(e), None),
+        };
+
+        if attempt > retries {
+            return Err(error.context(format!("gave up after {} attempts", attempt)));
+        }
+
+        let pause = retry_after.map_or(delay, |r| r.max(delay)).min(MAX_DELAY);
+        println!(
+            "{} failed ({}), retry {}/{} in {}ms",
+            name,
+            error,
+            attempt,
+            retries,
+            pause.as_millis()
+        );
+        tokio::time::sleep(pause).await;
+        delay = (delay * 2).min(MAX_DELAY);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::alert::AlertEvent;
+use crate::config::{AlertRule, Config, Schedule};
+use crate::models::service::Severity;
+use chrono::{DateTime, Datelike, FixedOffset, Utc};
+use std::collections::HashMap;
+
+/// Decides which notifiers receive an alert.
+///
+/// A service's own `notifiers` get its alerts unless a matching rule sets
+/// `replace`; `alert_rules` add more based on the service name, tags and
+/// severity, the failing checks, the alert kind and the time.
+pub struct Router {
+    rules: Vec<AlertRule>,
+    services: HashMap<String, ServiceRoute>,
+}
+
+struct ServiceRoute {
+    notifiers: Vec<String>,
+    tags: Vec<String>,
+    severity: Severity,
+}
+
+impl Router {
+    pub fn new(config: &Config) -> Self {
+        let services = config
+            .services
+            .iter()
+            .map(|s| {
+                let route = ServiceRoute {
+                    notifiers: s.notifiers.clone(),
+                    tags: s.tags.clone(),
+                    severity: s.severity,
+                };
+                (s.name.clone(), route)
+            })
+            .collect();
+
+        Self {
+            rules: config.alert_rules.clone(),
+            services,
+        }
+    }
+
+    /// Notifier names for `event` at `now`, without duplicates.
+    pub fn route(&self, event: &AlertEvent, now: DateTime<Utc>) -> Vec<String> {
+        let Some(service) = self.services.get(&event.service) else {
+            return Vec::new();
+        };
+
+        let mut own = true;
+        let mut added = Vec::new();
+        for rule in &self.rules {
+            if !matches(rule, event, service, now) {
+                continue;
+            }
+            own &= !rule.replace;
+            added.extend(rule.notifiers.iter().cloned());
+            if !rule.continue_matching {
+                break;
+            }
+        }
+
+        let mut notifiers = if own {
+            service.notifiers.clone()
+        } else {
+            Vec::new()
+        };
+        notifiers.extend(added);
+
+        let mut seen = Vec::new();
+        notifiers.retain(|n| {
+            let new = !seen.contains(n);
+            seen.push(n.clone());
+            new
+        });
+        notifiers
+    }
+}
+
+fn matches(
+    rule: &AlertRule,
+    event: &AlertEvent,
+    service: &ServiceRoute,
+    now: DateTime<Utc>,
+) -> bool {
+    let checks = || event.failing_checks.iter().chain([&event.check_type]);
+
+    (rule.services.is_empty()
+        || rule
+            .services
+            .iter()
+            .any(|p| wildcard_matches(p, &event.service)))
+        && (rule.tags.is_empty() || rule.tags.iter().any(|t| service.tags.contains(t)))
+        && (rule.check_types.is_empty()
+            || rule
+                .check_types
+                .iter()
+                .any(|t| checks().any(|c| c.eq_ignore_ascii_case(t))))
+        && (rule.statuses.is_empty() || rule.statuses.contains(&event.kind))
+        && (rule.severities.is_empty() || rule.severities.contains(&service.severity))
+        && rule.schedule.as_ref().is_none_or(|s| in_schedule(s, now))
+}
+
+fn in_schedule(schedule: &Schedule, now: DateTime<Utc>) -> bool {
+    let offset: FixedOffset = schedule
+        .utc_offset
+        .parse()
+        .unwrap_or(FixedOffset::east_opt(0).unwrap());
+    let local = now.with_timezone(&offset);
+    let time = local.time();
+
+    // A window such as 22:00-06:00 wraps past midnight and belongs to the day it starts on.
+    let (day, inside) = if schedule.start <= schedule.end {
+        (
+            local.weekday(),
+            time >= schedule.start && time < schedule.end,
+        )
+    } else if time >= schedule.start {
+        (local.weekday(), true)
+    } else {
+        (local.weekday().pred(), time < schedule.end)
+    };
+
+    inside && (schedule.days.is_empty() || schedule.days.contains(&day))
+}
+
+/// Case-sensitive match where `*` stands for any run of characters.
+pub fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertKind;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2024-01-01 is a Monday.
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn schedule(value: serde_json::Value) -> Schedule {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn event(service: &str, kind: AlertKind) -> AlertEvent {
+        AlertEvent {
+            kind,
+            service: service.to_string(),
+            url: String::new(),
+            check_type: "HTTP".to_string(),
+            response_time_ms: None,
+            message: String::new(),
+            failing_checks: Vec::new(),
+            incident_id: 1,
+            started_at: at(1, 12, 0),
+            duration_seconds: None,
+            escalation_level: 0,
+            timestamp: at(1, 12, 0),
+        }
+    }
+
+    fn router(rules: serde_json::Value) -> Router {
+        let config: Config = serde_json::from_value(json!({
+            "services": [
+                { "name": "api-eu", "url": "", "checks": [], "notifiers": ["pager"], "tags": ["prod"] },
+                { "name": "web", "url": "", "checks": [], "notifiers": ["mail"] }
+            ],
+            "alert_rules": rules
+        }))
+        .unwrap();
+        Router::new(&config)
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_matches("api-*", "api-eu"));
+        assert!(wildcard_matches("*-eu", "api-eu"));
+        assert!(wildcard_matches("a*i*u", "api-eu"));
+        assert!(wildcard_matches("*", ""));
+        assert!(wildcard_matches("api", "api"));
+        assert!(!wildcard_matches("api", "api-eu"));
+        assert!(!wildcard_matches("API-*", "api-eu"));
+        assert!(!wildcard_matches("a*a", "a"));
+        assert!(!wildcard_matches("*-us", "api-eu"));
+    }
+
+    #[test]
+    fn schedules() {
+        let office = schedule(json!({ "days": ["Mon", "Fri"], "start": "09:00", "end": "18:00" }));
+        assert!(in_schedule(&office, at(1, 9, 0)));
+        assert!(!in_schedule(&office, at(1, 18, 0)));
+        assert!(!in_schedule(&office, at(2, 12, 0)));
+        assert!(in_schedule(&office, at(5, 17, 59)));
+
+        let shifted = schedule(json!({ "start": "09:00", "end": "18:00", "utc_offset": "+02:00" }));
+        assert!(in_schedule(&shifted, at(1, 7, 0)));
+        assert!(!in_schedule(&shifted, at(1, 16, 0)));
+    }
+
+    #[test]
+    fn night_schedule_belongs_to_its_start_day() {
+        let night = schedule(json!({ "days": ["Fri"], "start": "22:00", "end": "06:00" }));
+        assert!(in_schedule(&night, at(5, 23, 0)));
+        assert!(in_schedule(&night, at(6, 5, 59)));
+        assert!(!in_schedule(&night, at(6, 6, 0)));
+        assert!(!in_schedule(&night, at(5, 5, 0)));
+        assert!(!in_schedule(&night, at(6, 23, 0)));
+    }
+
+    #[test]
+    fn rules_add_to_service_notifiers() {
+        let router = router(json!([
+            { "tags": ["prod"], "notifiers": ["chat", "pager"], "continue": true },
+            { "services": ["api-*"], "notifiers": ["mail"] },
+            { "notifiers": ["never"] }
+        ]));
+        assert_eq!(
+            router.route(&event("api-eu", AlertKind::Down), at(1, 12, 0)),
+            ["pager", "chat", "mail"]
+        );
+        assert_eq!(
+            router.route(&event("web", AlertKind::Down), at(1, 12, 0)),
+            ["mail", "never"]
+        );
+        assert!(router
+            .route(&event("unknown", AlertKind::Down), at(1, 12, 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn replace_drops_service_notifiers() {
+        let router = router(json!([
+            { "statuses": ["Degraded"], "notifiers": ["chat"], "replace": true },
+            { "tags": ["prod"], "notifiers": ["mail"], "replace": true, "continue": true },
+            { "services": ["api-*"], "notifiers": ["sms"] }
+        ]));
+        assert_eq!(
+            router.route(&event("web", AlertKind::Degraded), at(1, 12, 0)),
+            ["chat"]
+        );
+        assert_eq!(
+            router.route(&event("api-eu", AlertKind::Down), at(1, 12, 0)),
+            ["mail", "sms"]
+        );
+        assert_eq!(
+            router.route(&event("web", AlertKind::Down), at(1, 12, 0)),
+            ["mail"]
+        );
+    }
+}
@@ -1,16 +1,175 @@
-use reqwest::Client;
-use serde_json::json;
-
-#[allow(dead_code)]
-pub async fn send_slack(text: &str, webhook_url: &str) {
-    let payload = json!({ "text": text });
-    let body = serde_json::to_vec(&payload).unwrap();
-
-    let client = Client::new();
-    let _ = client
-        .post(webhook_url)
-        .header("Content-Type", "application/json")
-        .body(body)
-        .send()
-        .await;
+use crate::alert::retry::send_with_retries;
+use crate::alert::template::render;
+use crate::alert::{AlertEvent, AlertKind};
+use crate::config::SlackConfig;
+use crate::utils::time::format_duration;
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const DOWN_TEXT: &str = "[DOWN] {{service}} ({{checks}}): {{message}}";
+const ESCALATED_TEXT: &str =
+    "[ESCALATED] {{service}} still down and unacknowledged (level {{escalation_level}})";
+const FLAPPING_TEXT: &str = "[FLAPPING] {{service}} ({{checks}}): {{message}}";
+const DEGRADED_TEXT: &str = "[DEGRADED] {{service}} ({{checks}}): {{message}}";
+const RECOVERED_TEXT: &str = "[RECOVERED] {{service}} after {{duration}}";
+
+/// Posts alerts to a Slack incoming webhook as Block Kit messages.
+///
+/// The blocks sit in an attachment so the message gets a status colour bar;
+/// `text` is what Slack shows in notifications.
+pub struct SlackNotifier {
+    client: Client,
+    config: SlackConfig,
+}
+
+impl SlackNotifier {
+    pub fn new(config: &SlackConfig) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+        })
+    }
+
+    pub async fn send(&self, event: &AlertEvent) -> anyhow::Result<()> {
+        let body = self.payload(event).to_string();
+        send_with_retries(
+            "Slack",
+            self.config.retries,
+            Duration::from_millis(self.config.retry_delay_ms),
+            || {
+                self.client
+                    .post(&self.config.webhook_url)
+                    .header("Content-Type", "application/json")
+                    .body(body.clone())
+            },
+        )
+        .await
+    }
+
+    fn payload(&self, event: &AlertEvent) -> Value {
+        let (color, title, text) = match event.kind {
+            AlertKind::Down => (
+                "#e01e5a",
+                format!(":red_circle: {} is down", event.service),
+                DOWN_TEXT,
+            ),
+            AlertKind::Escalated => (
+                "#ecb22e",
+                format!(
+                    ":rotating_light: {} still down (escalation level {})",
+                    event.service, event.escalation_level
+                ),
+                ESCALATED_TEXT,
+            ),
+            AlertKind::Flapping => (
+                "#e8912d",
+                format!(":warning: {} is flapping", event.service),
+                FLAPPING_TEXT,
+            ),
+            AlertKind::Degraded => (
+                "#f2c744",
+                format!(":large_yellow_circle: {} is degraded", event.service),
+                DEGRADED_TEXT,
+            ),
+            AlertKind::Recovered => (
+                "#2eb67d",
+                format!(":large_green_circle: {} recovered", event.service),
+                RECOVERED_TEXT,
+            ),
+        };
+        let text = self.config.text.as_deref().unwrap_or(text);
+
+        let mut fields = vec![
+            field("Status", event.kind.label()),
+            field("Failing checks", &escape(&event.failing_checks.join(", "))),
+            field("Since", &slack_date(event)),
+        ];
+        if let Some(ms) = event.response_time_ms {
+            fields.push(field(
+                &format!("Latency ({})", escape(&event.check_type)),
+                &format!("{}ms", ms),
+            ));
+        }
+        if let Some(seconds) = event.duration_seconds {
+            fields.push(field("Downtime", &format_duration(seconds)));
+        }
+
+        let mut blocks = vec![
+            json!({
+                "type": "header",
+                "text": { "type": "plain_text", "text": title, "emoji": true },
+            }),
+            json!({ "type": "section", "fields": fields }),
+        ];
+        if event.kind != AlertKind::Recovered && !event.message.is_empty() {
+            blocks.push(json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": format!("```{}```", escape(&event.message)) },
+            }));
+        }
+
+        let mut buttons = Vec::new();
+        if let Some(dashboard) = &self.config.dashboard_url {
+            let dashboard = dashboard.trim_end_matches('/');
+            buttons.push(button("Open dashboard", dashboard.to_string()));
+            if let Ok(incidents) = Url::parse_with_params(
+                &format!("{}/api/incidents", dashboard),
+                [("service", &event.service)],
+            ) {
+                buttons.push(button("Incident details", incidents.into()));
+            }
+        }
+        if event.url.starts_with("http://") || event.url.starts_with("https://") {
+            buttons.push(button("Open service", event.url.clone()));
+        }
+        if !buttons.is_empty() {
+            blocks.push(json!({ "type": "actions", "elements": buttons }));
+        }
+
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{
+                "type": "mrkdwn",
+                "text": format!("Incident #{} · {}", event.incident_id, escape(&event.url)),
+            }],
+        }));
+
+        json!({
+            "text": render(text, event),
+            "attachments": [{ "color": color, "blocks": blocks }],
+        })
+    }
+}
+
+/// Section field; `value` is mrkdwn and must already be escaped.
+fn field(label: &str, value: &str) -> Value {
+    json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", label, value) })
+}
+
+fn button(text: &str, url: String) -> Value {
+    json!({
+        "type": "button",
+        "text": { "type": "plain_text", "text": text },
+        "url": url,
+    })
+}
+
+/// Incident start rendered in each reader's time zone.
+fn slack_date(event: &AlertEvent) -> String {
+    format!(
+        "<!date^{}^{{date_short_pretty}} {{time}}|{}>",
+        event.started_at.timestamp(),
+        event.started_at.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
@@ -0,0 +1,119 @@
+use crate::alert::AlertEvent;
+use crate::utils::time::format_duration;
+use chrono::SecondsFormat;
+
+/// Fills `{{placeholder}}`s in `template` with fields of `event`.
+///
+/// Available placeholders: `service`, `url`, `status` (e.g. `DOWN`, `DEGRADED`),
+/// `check_type`, `response_time_ms`, `message`, `checks`, `incident_id`,
+/// `started_at`, `timestamp`, `duration`, `duration_seconds` and
+/// `escalation_level`. Unknown placeholders are left as they are.
+pub fn render(template: &str, event: &AlertEvent) -> String {
+    render_with(template, event, |value| value.to_string())
+}
+
+/// Like [`render`], with values escaped for HTML.
+pub fn render_html(template: &str, event: &AlertEvent) -> String {
+    render_with(template, event, escape_html)
+}
+
+/// Renders every string in a JSON template.
+///
+/// A string that consists of a single placeholder is replaced by the typed
+/// value, so `"{{response_time_ms}}"` becomes a number (or `null`).
+pub fn render_json(template: &serde_json::Value, event: &AlertEvent) -> serde_json::Value {
+    use serde_json::Value;
+
+    match template {
+        Value::String(text) => {
+            let whole = text
+                .trim()
+                .strip_prefix("{{")
+                .and_then(|t| t.strip_suffix("}}"))
+                .filter(|name| !name.contains("{{"))
+                .and_then(|name| typed_value(name.trim(), event));
+            whole.unwrap_or_else(|| Value::String(render(text, event)))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_json(v, event)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), render_json(v, event)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn typed_value(name: &str, event: &AlertEvent) -> Option<serde_json::Value> {
+    let value = match name {
+        "response_time_ms" => serde_json::json!(event.response_time_ms),
+        "duration_seconds" => serde_json::json!(event.duration_seconds),
+        "incident_id" => serde_json::json!(event.incident_id),
+        "escalation_level" => serde_json::json!(event.escalation_level),
+        "checks" => serde_json::json!(event.failing_checks),
+        _ => serde_json::Value::String(value(name, event)?),
+    };
+    Some(value)
+}
+
+fn render_with(template: &str, event: &AlertEvent, escape: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        match value(after[..end].trim(), event) {
+            Some(value) => rendered.push_str(&escape(&value)),
+            None => rendered.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &after[end + 2..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+fn value(name: &str, event: &AlertEvent) -> Option<String> {
+    let value = match name {
+        "service" => event.service.clone(),
+        "url" => event.url.clone(),
+        "status" => event.kind.label().to_string(),
+        "check_type" => event.check_type.clone(),
+        "response_time_ms" => event
+            .response_time_ms
+            .map(|ms| ms.to_string())
+            .unwrap_or_default(),
+        "message" => event.message.clone(),
+        "checks" => event.failing_checks.join(", "),
+        "incident_id" => event.incident_id.to_string(),
+        "escalation_level" => event.escalation_level.to_string(),
+        "started_at" => event.started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "timestamp" => event.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "duration" => event
+            .duration_seconds
+            .map(format_duration)
+            .unwrap_or_default(),
+        "duration_seconds" => event
+            .duration_seconds
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -1 +1,96 @@
-/* TODO */
+use crate::alert::retry::send_with_retries;
+use crate::alert::template::render_json;
+use crate::alert::AlertEvent;
+use crate::config::{WebhookConfig, WebhookFormat};
+use reqwest::{Client, Method};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Sends alerts as HTTP requests with a templated JSON or form body.
+pub struct WebhookNotifier {
+    client: Client,
+    method: Method,
+    config: WebhookConfig,
+    body: Value,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: &WebhookConfig) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+
+        Ok(Self {
+            client,
+            method: Method::from_bytes(config.method.to_ascii_uppercase().as_bytes())?,
+            body: config.body.clone().unwrap_or_else(default_body),
+            config: config.clone(),
+        })
+    }
+
+    /// Sends `event`, retrying as configured.
+    pub async fn send(&self, event: &AlertEvent) -> anyhow::Result<()> {
+        let body = render_json(&self.body, event);
+        send_with_retries(
+            "Webhook",
+            self.config.retries,
+            Duration::from_millis(self.config.retry_delay_ms),
+            || self.request(&body),
+        )
+        .await
+    }
+
+    fn request(&self, body: &Value) -> reqwest::RequestBuilder {
+        let mut request = self.client.request(self.method.clone(), &self.config.url);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+
+        match self.config.format {
+            WebhookFormat::Json => request
+                .header("Content-Type", "application/json")
+                .body(body.to_string()),
+            WebhookFormat::Form => request.form(&form_fields(body)),
+        }
+    }
+}
+
+fn default_body() -> Value {
+    json!({
+        "service": "{{service}}",
+        "url": "{{url}}",
+        "status": "{{status}}",
+        "check_type": "{{check_type}}",
+        "response_time_ms": "{{response_time_ms}}",
+        "message": "{{message}}",
+        "failing_checks": "{{checks}}",
+        "incident_id": "{{incident_id}}",
+        "started_at": "{{started_at}}",
+        "timestamp": "{{timestamp}}",
+        "duration_seconds": "{{duration_seconds}}",
+        "escalation_level": "{{escalation_level}}",
+    })
+}
+
+fn form_fields(body: &Value) -> Vec<(String, String)> {
+    let Value::Object(fields) = body else {
+        return Vec::new();
+    };
+
+    fields
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Null => String::new(),
+                Value::Array(items) => items
+                    .iter()
+                    .map(|i| i.as_str().map_or_else(|| i.to_string(), str::to_string))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                other => other.to_string(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
@@ -1,16 +1,639 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 
-use crate::models::service::Service;
+use crate::alert::AlertKind;
+use crate::maintenance::Maintenance;
+use crate::models::maintenance::{default_utc_offset, MaintenanceWindow};
+use crate::models::service::{CheckType, Service, Severity};
+use crate::models::window::TimeWindow;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
     pub services: Vec<Service>,
+    #[serde(default)]
+    pub http_clients: HashMap<String, HttpClientConfig>,
+    #[serde(default = "default_history_path")]
+    pub history_path: String,
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u32,
+    #[serde(default = "default_history_buffer_size")]
+    pub history_buffer_size: usize,
+    #[serde(default = "default_uptime_windows")]
+    pub uptime_windows: Vec<TimeWindow>,
+    #[serde(default = "default_latency_windows")]
+    pub latency_windows: Vec<TimeWindow>,
+    #[serde(default)]
+    pub notifiers: HashMap<String, NotifierConfig>,
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRule>,
+    #[serde(default)]
+    pub escalation_policies: HashMap<String, EscalationPolicy>,
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+}
+
+/// Notifier tiers alerted while an incident stays open and unacknowledged.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    pub tiers: Vec<EscalationTier>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EscalationTier {
+    /// Minutes after the incident opened, or after the service went down if
+    /// the incident opened as degraded.
+    pub after_minutes: u64,
+    pub notifiers: Vec<String>,
+}
+
+/// Sends matching alerts to `notifiers`.
+///
+/// Rules are evaluated in order and the first match wins unless it sets
+/// `continue`. Every condition is optional; an empty list matches anything.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Service name patterns, `*` matching any run of characters.
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// Matches services with any of these tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Matches when any failing check has one of these types (`HTTP`, `TCP`, ...).
+    #[serde(default)]
+    pub check_types: Vec<String>,
+    #[serde(default)]
+    pub statuses: Vec<AlertKind>,
+    #[serde(default)]
+    pub severities: Vec<Severity>,
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    pub notifiers: Vec<String>,
+    #[serde(default, rename = "continue")]
+    pub continue_matching: bool,
+    /// Drops the service's own `notifiers` instead of adding to them.
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Weekly time window, e.g. business hours.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Defaults to every day.
+    #[serde(default)]
+    pub days: Vec<chrono::Weekday>,
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+    /// Offset the times are given in, e.g. `+02:00`.
+    #[serde(default = "default_utc_offset")]
+    pub utc_offset: String,
+}
+
+fn default_history_path() -> String {
+    "history.db".to_string()
+}
+
+/// Longest `history_retention_days`; it bounds every window, which keeps
+/// `now - window` within the range `chrono` can represent.
+const MAX_HISTORY_RETENTION_DAYS: u32 = 3650;
+
+fn default_history_retention_days() -> u32 {
+    90
+}
+
+fn default_history_buffer_size() -> usize {
+    1440
+}
+
+fn default_uptime_windows() -> Vec<TimeWindow> {
+    default_windows(&["24h", "7d", "30d", "90d"])
+}
+
+fn default_latency_windows() -> Vec<TimeWindow> {
+    default_windows(&["1h", "24h", "7d"])
+}
+
+fn default_windows(windows: &[&str]) -> Vec<TimeWindow> {
+    windows
+        .iter()
+        .map(|w| TimeWindow::parse(w).expect("valid default window"))
+        .collect()
+}
+
+/// Named HTTP client profile, selected per `Http` check with `http_client`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HttpClientConfig {
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
+    #[serde(default)]
+    pub client_cert: Option<String>,
+    #[serde(default)]
+    pub client_key: Option<String>,
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub http_version: HttpVersion,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum HttpVersion {
+    #[default]
+    Auto,
+    Http1,
+    Http2,
+}
+
+/// Named alert destination.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum NotifierConfig {
+    Email(EmailConfig),
+    Webhook(WebhookConfig),
+    Slack(SlackConfig),
+    PagerDuty(PagerDutyConfig),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PagerDutyConfig {
+    /// Integration key of an Events API v2 integration.
+    pub routing_key: String,
+    /// Override for testing against a local stand-in.
+    #[serde(default = "default_pagerduty_url")]
+    pub events_url: String,
+    #[serde(default)]
+    pub severity: PagerDutySeverity,
+    /// Severity of alerts for degraded services.
+    #[serde(default = "default_degraded_severity")]
+    pub degraded_severity: PagerDutySeverity,
+    #[serde(default = "default_dedup_key_prefix")]
+    pub dedup_key_prefix: String,
+    #[serde(default)]
+    pub dashboard_url: Option<String>,
+    #[serde(default = "default_delivery_retries")]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    #[serde(default = "default_notifier_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_pagerduty_url() -> String {
+    "https://events.pagerduty.com/v2/enqueue".to_string()
+}
+
+fn default_degraded_severity() -> PagerDutySeverity {
+    PagerDutySeverity::Warning
+}
+
+fn default_dedup_key_prefix() -> String {
+    "service-health-monitor".to_string()
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PagerDutySeverity {
+    #[default]
+    Critical,
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+    /// Notification (fallback) text template; defaults to a one-line summary.
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Base URL of this monitor's dashboard, linked from messages.
+    #[serde(default)]
+    pub dashboard_url: Option<String>,
+    #[serde(default = "default_delivery_retries")]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    #[serde(default = "default_notifier_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_delivery_retries() -> u32 {
+    3
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default = "default_webhook_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// JSON template; defaults to the whole alert event.
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    #[serde(default = "default_webhook_retries")]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    #[serde(default = "default_notifier_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+
+fn default_webhook_retries() -> u32 {
+    2
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+fn default_notifier_timeout_ms() -> u64 {
+    10_000
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum WebhookFormat {
+    #[default]
+    Json,
+    /// `application/x-www-form-urlencoded`; `body` must be a flat object.
+    Form,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub host: String,
+    /// Defaults to 25, 587 or 465 depending on `tls`.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "default_notifier_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub text_body: Option<String>,
+    #[serde(default)]
+    pub html_body: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum SmtpTls {
+    /// Plain SMTP, e.g. for a local relay or test sink.
+    None,
+    #[default]
+    StartTls,
+    /// TLS from the start of the connection (SMTPS).
+    Implicit,
 }
 
 impl Config {
     pub fn load(path: &str) -> anyhow::Result<Self> {
         let data = fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&data)?)
+        let mut config: Config = serde_json::from_str(&data)?;
+        // Ids are reserved for windows created through the API.
+        for window in &mut config.maintenance_windows {
+            window.id = None;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// A `depends_on` loop, e.g. `["a", "b", "a"]`, if there is one.
+    fn dependency_cycle(&self) -> Option<Vec<String>> {
+        fn visit<'a>(
+            config: &'a Config,
+            name: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut Vec<&'a str>,
+        ) -> Option<Vec<String>> {
+            if let Some(start) = path.iter().position(|n| *n == name) {
+                let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(name.to_string());
+                return Some(cycle);
+            }
+            if done.contains(&name) {
+                return None;
+            }
+
+            path.push(name);
+            let service = config.services.iter().find(|s| s.name == name)?;
+            for dependency in &service.depends_on {
+                if let Some(cycle) = visit(config, dependency, path, done) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            done.push(name);
+            None
+        }
+
+        let mut done = Vec::new();
+        self.services
+            .iter()
+            .find_map(|s| visit(self, &s.name, &mut Vec::new(), &mut done))
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        for service in &self.services {
+            for name in &service.notifiers {
+                if !self.notifiers.contains_key(name) {
+                    anyhow::bail!(
+                        "service '{}' uses unknown notifier '{}'",
+                        service.name,
+                        name
+                    );
+                }
+            }
+            for check in &service.checks {
+                if let Some(name) = &check.http_client {
+                    if !self.http_clients.contains_key(name) {
+                        anyhow::bail!(
+                            "service '{}' uses unknown http_client '{}'",
+                            service.name,
+                            name
+                        );
+                    }
+                }
+                if !(3..=100).contains(&check.flap_history) {
+                    anyhow::bail!(
+                        "service '{}' {} check: flap_history must be between 3 and 100",
+                        service.name,
+                        check.check_type.label()
+                    );
+                }
+                if check.flap_low_threshold >= check.flap_high_threshold {
+                    anyhow::bail!(
+                        "service '{}' {} check: flap_low_threshold must be below flap_high_threshold",
+                        service.name,
+                        check.check_type.label()
+                    );
+                }
+                if matches!(check.check_type, CheckType::Http) {
+                    let method = check.method.to_ascii_uppercase();
+                    if reqwest::Method::from_bytes(method.as_bytes()).is_err() {
+                        anyhow::bail!(
+                            "service '{}' {} check: invalid method '{}'",
+                            service.name,
+                            check.check_type.label(),
+                            check.method
+                        );
+                    }
+                    for (name, value) in &check.headers {
+                        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                            anyhow::bail!(
+                                "service '{}' {} check: invalid header name '{}'",
+                                service.name,
+                                check.check_type.label(),
+                                name
+                            );
+                        }
+                        if reqwest::header::HeaderValue::from_str(value).is_err() {
+                            anyhow::bail!(
+                                "service '{}' {} check: invalid value for header '{}'",
+                                service.name,
+                                check.check_type.label(),
+                                name
+                            );
+                        }
+                    }
+                }
+                if let (Some(warning), Some(critical)) = (
+                    check.response_time_warning_ms,
+                    check.response_time_critical_ms,
+                ) {
+                    if warning >= critical {
+                        anyhow::bail!(
+                            "service '{}' {} check: response_time_warning_ms must be below response_time_critical_ms",
+                            service.name,
+                            check.check_type.label()
+                        );
+                    }
+                }
+            }
+        }
+
+        if !(1..=MAX_HISTORY_RETENTION_DAYS).contains(&self.history_retention_days) {
+            anyhow::bail!(
+                "history_retention_days must be between 1 and {}",
+                MAX_HISTORY_RETENTION_DAYS
+            );
+        }
+        let retention = chrono::Duration::days(self.history_retention_days as i64);
+        let windows = [
+            ("uptime", &self.uptime_windows),
+            ("latency", &self.latency_windows),
+        ];
+        for (kind, windows) in windows {
+            for window in windows {
+                if window.duration() > retention {
+                    anyhow::bail!(
+                        "{} window '{}' is longer than history_retention_days ({})",
+                        kind,
+                        window.label(),
+                        self.history_retention_days
+                    );
+                }
+            }
+        }
+
+        for service in &self.services {
+            for dependency in &service.depends_on {
+                if !self.services.iter().any(|s| &s.name == dependency) {
+                    anyhow::bail!(
+                        "service '{}' depends on unknown service '{}'",
+                        service.name,
+                        dependency
+                    );
+                }
+            }
+        }
+        if let Some(cycle) = self.dependency_cycle() {
+            anyhow::bail!("services depend on each other: {}", cycle.join(" -> "));
+        }
+
+        for service in &self.services {
+            if let Some(policy) = &service.escalation_policy {
+                if !self.escalation_policies.contains_key(policy) {
+                    anyhow::bail!(
+                        "service '{}' uses unknown escalation_policy '{}'",
+                        service.name,
+                        policy
+                    );
+                }
+            }
+        }
+
+        for (name, policy) in &self.escalation_policies {
+            for tier in &policy.tiers {
+                if let Some(unknown) = tier
+                    .notifiers
+                    .iter()
+                    .find(|n| !self.notifiers.contains_key(*n))
+                {
+                    anyhow::bail!(
+                        "escalation policy '{}' uses unknown notifier '{}'",
+                        name,
+                        unknown
+                    );
+                }
+            }
+            if policy
+                .tiers
+                .windows(2)
+                .any(|t| t[0].after_minutes > t[1].after_minutes)
+            {
+                anyhow::bail!(
+                    "escalation policy '{}' must list tiers by increasing after_minutes",
+                    name
+                );
+            }
+        }
+
+        for (index, window) in self.maintenance_windows.iter().enumerate() {
+            if let Err(e) = Maintenance::new(window.clone()) {
+                let name = if window.name.is_empty() {
+                    format!("#{}", index + 1)
+                } else {
+                    window.name.clone()
+                };
+                anyhow::bail!("maintenance window {}: {}", name, e);
+            }
+        }
+
+        for (index, rule) in self.alert_rules.iter().enumerate() {
+            let rule_name = rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("#{}", index + 1));
+            for name in &rule.notifiers {
+                if !self.notifiers.contains_key(name) {
+                    anyhow::bail!("alert rule {} uses unknown notifier '{}'", rule_name, name);
+                }
+            }
+            if let Some(schedule) = &rule.schedule {
+                if schedule.utc_offset.parse::<chrono::FixedOffset>().is_err() {
+                    anyhow::bail!(
+                        "alert rule {} has invalid utc_offset '{}' (use e.g. +02:00)",
+                        rule_name,
+                        schedule.utc_offset
+                    );
+                }
+            }
+        }
+
+        for (name, notifier) in &self.notifiers {
+            match notifier {
+                NotifierConfig::Email(email) => {
+                    if email.to.is_empty() {
+                        anyhow::bail!("email notifier '{}' has no recipients in 'to'", name);
+                    }
+                    if email.username.is_some() != email.password.is_some() {
+                        anyhow::bail!("email notifier '{}' needs both username and password", name);
+                    }
+                }
+                NotifierConfig::Webhook(webhook) => {
+                    reqwest::Method::from_bytes(webhook.method.to_ascii_uppercase().as_bytes())
+                        .map_err(|_| {
+                            anyhow::anyhow!("webhook notifier '{}' has invalid method", name)
+                        })?;
+                    let flat = match &webhook.body {
+                        None => true,
+                        Some(serde_json::Value::Object(fields)) => {
+                            fields.values().all(|v| !v.is_object() && !v.is_array())
+                        }
+                        Some(_) => false,
+                    };
+                    if matches!(webhook.format, WebhookFormat::Form) && !flat {
+                        anyhow::bail!(
+                            "webhook notifier '{}' uses Form format but body is not a flat object",
+                            name
+                        );
+                    }
+                }
+                NotifierConfig::Slack(_) => {}
+                NotifierConfig::PagerDuty(pagerduty) => {
+                    if pagerduty.routing_key.trim().is_empty() {
+                        anyhow::bail!("pagerduty notifier '{}' needs a routing_key", name);
+                    }
+                }
+            }
+        }
+
+        for (name, client) in &self.http_clients {
+            if client.client_cert.is_some() != client.client_key.is_some() {
+                anyhow::bail!(
+                    "http_client '{}' needs both client_cert and client_key",
+                    name
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_check(check: serde_json::Value) -> Config {
+        let mut fields = serde_json::json!({
+            "check_type": "Http",
+            "interval_seconds": 30,
+            "timeout_ms": 1000
+        });
+        fields
+            .as_object_mut()
+            .unwrap()
+            .extend(check.as_object().unwrap().clone());
+        serde_json::from_value(serde_json::json!({
+            "services": [{ "name": "api", "url": "http://127.0.0.1", "checks": [fields] }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validates_http_method_and_headers() {
+        let valid = http_check(serde_json::json!({
+            "method": "post",
+            "headers": { "Authorization": "Bearer token" }
+        }));
+        assert!(valid.validate().is_ok());
+
+        for (check, error) in [
+            (
+                serde_json::json!({ "method": "GE T" }),
+                "service 'api' HTTP check: invalid method 'GE T'",
+            ),
+            (
+                serde_json::json!({ "headers": { "X Token": "1" } }),
+                "service 'api' HTTP check: invalid header name 'X Token'",
+            ),
+            (
+                serde_json::json!({ "headers": { "X-Token": "a\nb" } }),
+                "service 'api' HTTP check: invalid value for header 'X-Token'",
+            ),
+        ] {
+            let invalid = http_check(check).validate().unwrap_err();
+            assert_eq!(invalid.to_string(), error);
+        }
     }
 }
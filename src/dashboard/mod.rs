@@ -1,5 +1,5 @@
 use crate::state::AppState;
-use axum::routing::get;
+use axum::routing::{delete, get, post};
 use axum::{Extension, Router};
 use tokio::net::TcpListener;
 
@@ -9,6 +9,18 @@ pub async fn start_dashboard(_config: crate::config::Config, state: AppState) {
     let app = Router::new()
         .route("/", get(routes::index))
         .route("/api/status", get(routes::status))
+        .route(
+            "/api/services/:name/checks/:check/history",
+            get(routes::check_history),
+        )
+        .route("/api/incidents", get(routes::incidents))
+        .route("/api/incidents/:id/ack", post(routes::acknowledge_incident))
+        .route(
+            "/api/maintenance",
+            get(routes::maintenance).post(routes::create_maintenance),
+        )
+        .route("/api/maintenance/:id", delete(routes::delete_maintenance))
+        .route("/api/notifications", get(routes::notifications))
         .layer(Extension(state));
 
     let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
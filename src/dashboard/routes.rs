@@ -1,5 +1,10 @@
-use crate::state::AppState;
+use crate::models::maintenance::MaintenanceWindow;
+use crate::state::{AckError, AppState};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
 use axum::{response::Html, Extension, Json};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 
 pub async fn index() -> Html<&'static str> {
     Html(
@@ -132,11 +137,73 @@ pub async fn index() -> Html<&'static str> {
             color: #991b1b;
         }
         
+        .status-badge.expiringsoon {
+            background: #fef3c7;
+            color: #92400e;
+        }
+        
+        .status-badge.degraded {
+            background: #fef9c3;
+            color: #854d0e;
+        }
+        
         .status-badge.unknown {
             background: #e5e7eb;
             color: #4b5563;
         }
         
+        .status-badge.maintenance {
+            background: #dbeafe;
+            color: #1e40af;
+        }
+        
+        .status-badge.impacted {
+            background: #ede9fe;
+            color: #5b21b6;
+        }
+        
+        .status-badge.flapping {
+            background: #ffedd5;
+            color: #9a3412;
+        }
+        
+        .dependencies {
+            background: white;
+            border-radius: 12px;
+            padding: 24px;
+            box-shadow: 0 4px 6px rgba(0,0,0,0.1);
+            margin-bottom: 30px;
+        }
+        
+        .dependencies h2 {
+            font-size: 1.1em;
+            color: #1f2937;
+            margin-bottom: 12px;
+        }
+        
+        .dependency-tree, .dependency-tree ul {
+            list-style: none;
+        }
+        
+        .dependency-tree ul {
+            margin-left: 14px;
+            padding-left: 16px;
+            border-left: 2px solid #e5e7eb;
+        }
+        
+        .dependency-tree li {
+            margin: 8px 0;
+            color: #1f2937;
+            font-weight: 600;
+        }
+        
+        .dependency-tree .status-badge {
+            display: inline-block;
+            margin-right: 8px;
+            padding: 2px 10px;
+            font-size: 0.7em;
+        }
+        
         .service-url {
             color: #6b7280;
             font-size: 0.9em;
@@ -240,11 +307,26 @@ pub async fn index() -> Html<&'static str> {
             color: #991b1b;
         }
 
+        .check-chip.expiringsoon {
+            background: #fef3c7;
+            color: #92400e;
+        }
+
+        .check-chip.degraded {
+            background: #fef9c3;
+            color: #854d0e;
+        }
+
         .check-chip.unknown {
             background: #e5e7eb;
             color: #4b5563;
         }
 
+        .check-chip.flapping {
+            background: #ffedd5;
+            color: #9a3412;
+        }
+
         .check-meta {
             display: grid;
             grid-template-columns: repeat(2, minmax(0, 1fr));
@@ -255,6 +337,49 @@ pub async fn index() -> Html<&'static str> {
             margin: 0;
         }
         
+        .timings {
+            margin-top: 10px;
+        }
+
+        .timing-bar {
+            display: flex;
+            width: 100%;
+            height: 8px;
+            border-radius: 4px;
+            overflow: hidden;
+            background: #e5e7eb;
+        }
+
+        .timing-bar span {
+            height: 100%;
+        }
+
+        .timing-legend {
+            display: flex;
+            flex-wrap: wrap;
+            gap: 10px;
+            margin-top: 6px;
+            font-size: 0.78em;
+            color: #4b5563;
+        }
+
+        .timing-legend i {
+            display: inline-block;
+            width: 8px;
+            height: 8px;
+            border-radius: 2px;
+            margin-right: 4px;
+        }
+
+        .uptime-windows {
+            display: flex;
+            flex-wrap: wrap;
+            gap: 10px;
+            margin-top: 8px;
+            font-size: 0.78em;
+            color: #4b5563;
+        }
+
         .next-check {
             margin-top: 12px;
             padding: 8px 0;
@@ -316,6 +441,8 @@ pub async fn index() -> Html<&'static str> {
             </div>
         </div>
         
+        <div class="dependencies" id="dependencies" style="display: none"></div>
+        
         <div class="services-grid" id="services">
             <div class="loading">Loading services...</div>
         </div>
@@ -334,6 +461,7 @@ pub async fn index() -> Html<&'static str> {
                 currentServices = data.services;
                 updateStats(data.services);
                 updateServices(data.services);
+                updateDependencies(data.services);
                 
                 document.getElementById('lastUpdated').textContent = 
                     'Last updated: ' + new Date().toLocaleTimeString();
@@ -392,7 +520,7 @@ pub async fn index() -> Html<&'static str> {
             const upCount = services.filter(s => s.status === 'Up').length;
             const downCount = services.filter(s => s.status === 'Down').length;
             const avgUptime = services.length > 0 
-                ? (services.reduce((sum, s) => sum + s.uptime_percentage, 0) / services.length).toFixed(1)
+                ? (services.reduce((sum, s) => sum + primaryUptime(s).value, 0) / services.length).toFixed(1)
                 : 0;
             const responseTimes = services
                 .filter(s => s.response_time_ms !== null)
@@ -426,8 +554,8 @@ pub async fn index() -> Html<&'static str> {
                     <div class="service-url">${escapeHtml(service.url)}</div>
                     <div class="service-details">
                         <div class="detail-item">
-                            <div class="detail-label">Uptime</div>
-                            <div class="detail-value">${service.uptime_percentage.toFixed(1)}%</div>
+                            <div class="detail-label">Uptime ${primaryUptime(service).label}</div>
+                            <div class="detail-value">${primaryUptime(service).value.toFixed(1)}%</div>
                         </div>
                         <div class="detail-item">
                             <div class="detail-label">Response</div>
@@ -446,13 +574,14 @@ pub async fn index() -> Html<&'static str> {
                             </div>
                         </div>
                     </div>
+                    ${renderUptimeWindows(service)}
                     ${service.message ? `<div class="message">${escapeHtml(service.message)}</div>` : ''}
                     <div class="checks">
                         ${(Array.isArray(service.checks) ? service.checks : []).map((check, cIndex) => `
                             <div class="check-row">
                                 <div class="check-row-header">
                                     <span class="check-chip ${check.status.toLowerCase()}">${escapeHtml(check.check_type)}</span>
-                                    <span class="detail-label">${escapeHtml(check.status)}</span>
+                                    <span class="detail-label" title="${check.state_change_percentage}% state change">${escapeHtml(check.status)}${renderPending(check)}</span>
                                 </div>
                                 <div class="check-meta">
                                     <div class="detail-item">
@@ -466,14 +595,18 @@ pub async fn index() -> Html<&'static str> {
                                         </div>
                                     </div>
                                     <div class="detail-item">
-                                        <div class="detail-label">Uptime</div>
-                                        <div class="detail-value">${check.uptime_percentage.toFixed(1)}%</div>
+                                        <div class="detail-label">Uptime ${primaryUptime(check).label}</div>
+                                        <div class="detail-value">${primaryUptime(check).value.toFixed(1)}%</div>
                                     </div>
                                     <div class="detail-item">
                                         <div class="detail-label">Last Check</div>
                                         <div class="detail-value">${formatTime(check.last_check)}</div>
                                     </div>
                                 </div>
+                                ${renderUptimeWindows(check)}
+                                ${renderLatency(check)}
+                                ${check.timings ? renderTimings(check.timings) : ''}
+                                ${check.certificate ? renderCertificate(check.certificate) : ''}
                                 ${check.message ? `<div class="message">${escapeHtml(check.message)}</div>` : ''}
                                 <div class="next-check">
                                     <div class="next-check-label">
@@ -493,6 +626,125 @@ pub async fn index() -> Html<&'static str> {
             updateProgressBars();
         }
         
+        function updateDependencies(services) {
+            const container = document.getElementById('dependencies');
+            const dependents = name => services
+                .filter(s => (s.depends_on || []).includes(name))
+                .sort((a, b) => a.name.localeCompare(b.name));
+            const roots = services
+                .filter(s => (s.depends_on || []).length === 0 && dependents(s.name).length > 0)
+                .sort((a, b) => a.name.localeCompare(b.name));
+            
+            if (roots.length === 0) {
+                container.style.display = 'none';
+                return;
+            }
+            
+            const node = service => `
+                <li>
+                    <span class="status-badge ${service.status.toLowerCase()}">${service.status}</span>${escapeHtml(service.name)}
+                    ${dependents(service.name).length > 0
+                        ? `<ul>${dependents(service.name).map(node).join('')}</ul>`
+                        : ''}
+                </li>
+            `;
+            container.innerHTML = `
+                <h2>Dependencies</h2>
+                <ul class="dependency-tree">${roots.map(node).join('')}</ul>
+            `;
+            container.style.display = '';
+        }
+        
+        // Uptime over the first configured window, falling back to the lifetime figure.
+        function primaryUptime(entity) {
+            const first = (entity.uptime_windows || [])[0];
+            if (first && first.uptime_percentage !== null) {
+                return { label: escapeHtml(first.window), value: first.uptime_percentage };
+            }
+            return { label: '', value: entity.uptime_percentage };
+        }
+        
+        function renderUptimeWindows(entity) {
+            const windows = entity.uptime_windows || [];
+            if (windows.length === 0) return '';
+            return `
+                <div class="uptime-windows">
+                    ${windows.map(w =>
+                        `<span>${escapeHtml(w.window)}: ${w.uptime_percentage !== null ? w.uptime_percentage.toFixed(2) + '%' : 'N/A'}</span>`
+                    ).join('')}
+                </div>
+            `;
+        }
+        
+        function renderLatency(check) {
+            const l = (check.latency || []).find(w => w.samples > 0);
+            if (!l) return '';
+            return `
+                <div class="uptime-windows" title="${l.samples} samples, min ${l.min_ms}ms, max ${l.max_ms}ms, stddev ${l.stddev_ms}ms">
+                    <span>Latency ${escapeHtml(l.window)}:</span>
+                    <span>p50 ${Math.round(l.p50_ms)}ms</span>
+                    <span>p95 ${Math.round(l.p95_ms)}ms</span>
+                    <span>p99 ${Math.round(l.p99_ms)}ms</span>
+                    <span>mean ${Math.round(l.mean_ms)}ms</span>
+                </div>
+            `;
+        }
+        
+        function renderPending(check) {
+            if (!check.pending_status) return '';
+            const count = check.pending_status === 'Down'
+                ? check.consecutive_failures + ' failed'
+                : check.consecutive_successes + ' ok';
+            return ` &rarr; ${escapeHtml(check.pending_status)} pending (${count})`;
+        }
+        
+        function renderTimings(t) {
+            const phases = [
+                ['DNS', t.dns_ms, '#60a5fa'],
+                ['Connect', t.connect_ms, '#34d399'],
+                ['TTFB', t.ttfb_ms, '#fbbf24'],
+                ['Download', t.download_ms, '#a78bfa'],
+            ].filter(([, ms]) => ms !== null);
+            const total = Math.max(1, phases.reduce((sum, [, ms]) => sum + ms, 0));
+            return `
+                <div class="timings">
+                    <div class="timing-bar">
+                        ${phases.map(([label, ms, color]) =>
+                            `<span title="${label}: ${ms}ms" style="width: ${(ms / total) * 100}%; background: ${color};"></span>`
+                        ).join('')}
+                    </div>
+                    <div class="timing-legend">
+                        ${phases.map(([label, ms, color]) =>
+                            `<span><i style="background: ${color};"></i>${label} ${ms}ms</span>`
+                        ).join('')}
+                        ${t.connection_reused ? '<span>(reused connection)</span>' : ''}
+                    </div>
+                </div>
+            `;
+        }
+        
+        function renderCertificate(cert) {
+            const names = cert.subject_alt_names.length > 0
+                ? cert.subject_alt_names.join(', ')
+                : cert.subject;
+            return `
+                <div class="check-meta" style="margin-top: 10px;">
+                    <div class="detail-item">
+                        <div class="detail-label">Cert Expires</div>
+                        <div class="detail-value">${cert.days_until_expiry}d</div>
+                    </div>
+                    <div class="detail-item">
+                        <div class="detail-label">Host Match</div>
+                        <div class="detail-value">${cert.hostname_match ? 'Yes' : 'No'}</div>
+                    </div>
+                </div>
+                <div class="message">
+                    Issuer: ${escapeHtml(cert.issuer)}<br>
+                    Names: ${escapeHtml(names)}
+                </div>
+            `;
+        }
+        
         function formatTime(timestamp) {
             const date = new Date(timestamp);
             const now = new Date();
@@ -524,3 +776,124 @@ pub async fn status(Extension(state): Extension<AppState>) -> Json<serde_json::V
     let services = state.get_all_services().await;
     Json(serde_json::json!({ "services": services }))
 }
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+pub async fn check_history(
+    Extension(state): Extension<AppState>,
+    Path((name, check)): Path<(String, String)>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let history = state
+        .check_history(&name, &check, query.since, query.until)
+        .await;
+
+    match history {
+        Some(Ok(results)) => Ok(Json(serde_json::json!({
+            "service": name,
+            "check_type": check.to_ascii_uppercase(),
+            "results": results,
+        }))),
+        Some(Err(e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("unknown check {}/{}", name, check) })),
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct IncidentQuery {
+    service: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+pub async fn incidents(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<IncidentQuery>,
+) -> Json<serde_json::Value> {
+    let incidents = state
+        .incidents(query.service.as_deref(), query.since, query.until)
+        .await;
+    Json(serde_json::json!({ "incidents": incidents }))
+}
+
+#[derive(Deserialize)]
+pub struct AckRequest {
+    by: Option<String>,
+}
+
+pub async fn acknowledge_incident(
+    Extension(state): Extension<AppState>,
+    Path(id): Path<u64>,
+    request: Option<Json<AckRequest>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let by = request.and_then(|Json(request)| request.by);
+    match state.acknowledge_incident(id, by, Utc::now()).await {
+        Ok(incident) => Ok(Json(serde_json::json!({ "incident": incident }))),
+        Err(AckError::NotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("unknown incident {}", id) })),
+        )),
+        Err(AckError::Resolved) => Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": format!("incident {} is already resolved", id) })),
+        )),
+    }
+}
+
+pub async fn maintenance(Extension(state): Extension<AppState>) -> Json<serde_json::Value> {
+    let windows = state.maintenance_windows(Utc::now()).await;
+    Json(serde_json::json!({ "windows": windows }))
+}
+
+pub async fn create_maintenance(
+    Extension(state): Extension<AppState>,
+    Json(window): Json<MaintenanceWindow>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    match state.add_maintenance(window, Utc::now()).await {
+        Ok(window) => Ok((
+            StatusCode::CREATED,
+            Json(serde_json::json!({ "window": window })),
+        )),
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+pub async fn delete_maintenance(
+    Extension(state): Extension<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    match state.remove_maintenance(id).await {
+        Some(window) => Ok(Json(serde_json::json!({ "window": window }))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("unknown maintenance window {}", id) })),
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    failed: bool,
+}
+
+pub async fn notifications(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<NotificationQuery>,
+) -> Json<serde_json::Value> {
+    let deliveries = state.alert_deliveries(query.failed);
+    Json(serde_json::json!({ "deliveries": deliveries }))
+}
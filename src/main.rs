@@ -1,28 +1,42 @@
 mod alert;
 mod config;
 mod dashboard;
+mod maintenance;
 mod models;
 mod monitor;
 mod state;
+mod stats;
+mod storage;
 mod utils;
 
+use crate::alert::dispatcher::Dispatcher;
+use crate::alert::escalation::start_escalations;
 use crate::config::Config;
 use crate::dashboard::start_dashboard;
-use crate::monitor::start_monitoring;
+use crate::monitor::reload::watch_config;
+use crate::monitor::Monitor;
 use crate::state::AppState;
+use crate::storage::Store;
+
+const CONFIG_PATH: &str = "config.json";
 
 #[tokio::main]
 async fn main() {
     println!("Starting Service Health Monitor...");
 
-    let config = Config::load("config.json").expect("Failed to load config");
-    let state = AppState::new();
+    let config = Config::load(CONFIG_PATH).expect("Failed to load config");
+    let store = Store::open(&config.history_path, config.history_retention_days)
+        .expect("Failed to open history store");
+    let dispatcher = Dispatcher::new(&config).expect("Failed to set up notifiers");
+    let state = AppState::with_store(store, dispatcher, &config);
+    if let Err(e) = state.restore(&config).await {
+        println!("Failed to restore check history: {}", e);
+    }
+
+    let monitor = Monitor::start(config.clone(), state.clone());
+    tokio::spawn(watch_config(CONFIG_PATH, monitor));
 
-    let monitor_state = state.clone();
-    let monitor_config = config.clone();
-    tokio::spawn(async move {
-        start_monitoring(monitor_config, monitor_state).await;
-    });
+    tokio::spawn(start_escalations(state.clone()));
 
     start_dashboard(config, state).await;
 }
@@ -0,0 +1,262 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+const DAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// Standard five-field cron expression: minute, hour, day of month, month
+/// and day of week.
+///
+/// Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`),
+/// lists and, for months and weekdays, three-letter names. Day 0 and 7 are
+/// both Sunday. As in cron, when both day of month and day of week are
+/// restricted a day matching either one matches.
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> anyhow::Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            anyhow::bail!("cron expression '{}' must have 5 fields", expression);
+        };
+
+        let mut days_of_week = parse_field(day_of_week, 0, 7, &DAYS, 0)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0)?,
+            hours: parse_field(hour, 0, 23, &[], 0)?,
+            days_of_month: parse_field(day_of_month, 1, 31, &[], 1)?,
+            months: parse_field(month, 1, 12, &MONTHS, 1)?,
+            days_of_week,
+            any_day_of_month: day_of_month == "*",
+            any_day_of_week: day_of_week == "*",
+        })
+    }
+
+    /// The latest occurrence from `from` through `to`, both whole minutes.
+    pub fn latest(&self, from: NaiveDateTime, to: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut day = to.date();
+        while day >= from.date() {
+            if self.day_matches(day) {
+                let (last_hour, last_minute) = if day == to.date() {
+                    (to.hour(), to.minute())
+                } else {
+                    (23, 59)
+                };
+                for hour in (0..=last_hour).rev().filter(|h| bit(self.hours, *h)) {
+                    let up_to = if hour == last_hour { last_minute } else { 59 };
+                    if let Some(minute) = highest(self.minutes, up_to) {
+                        let at = day.and_hms_opt(hour, minute, 0)?;
+                        return (at >= from).then_some(at);
+                    }
+                }
+            }
+            day = day.pred_opt()?;
+        }
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day_of_month = bit(self.days_of_month, date.day());
+        let day_of_week = bit(self.days_of_week, date.weekday().num_days_from_sunday());
+        let day = match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        };
+
+        day && bit(self.months, date.month())
+    }
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// The highest value in `set` that is at most `max` (below 63).
+fn highest(set: u64, max: u32) -> Option<u32> {
+    let set = set & ((1 << (max + 1)) - 1);
+    (set != 0).then(|| 63 - set.leading_zeros())
+}
+
+/// Parses one field into a bit set; `names[i]` stands for `i + first_name`.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    first_name: u32,
+) -> anyhow::Result<u64> {
+    let value = |raw: &str| -> anyhow::Result<u32> {
+        let value = match names.iter().position(|n| n.eq_ignore_ascii_case(raw)) {
+            Some(index) => index as u32 + first_name,
+            None => raw
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid cron value '{}'", raw))?,
+        };
+        if value < min || value > max {
+            anyhow::bail!("cron value {} is outside {}-{}", value, min, max);
+        }
+        Ok(value)
+    };
+
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| anyhow::anyhow!("invalid cron step '{}'", step))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, mut end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        // Weekday ranges may end on Sunday, e.g. `FRI-SUN`.
+        if max == 7 && end == 0 && start > 0 {
+            end = 7;
+        }
+        if start > end {
+            anyhow::bail!("invalid cron range '{}'", range);
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// 2025-06-02 is a Monday.
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn matches(cron: &Cron, at: NaiveDateTime) -> bool {
+        cron.latest(at, at).is_some()
+    }
+
+    #[test]
+    fn weekday_names_and_ranges() {
+        let cron = Cron::parse("0 2 * * FRI-SUN").unwrap();
+        assert!(!matches(&cron, at(5, 2, 0)));
+        assert!(matches(&cron, at(6, 2, 0)));
+        assert!(matches(&cron, at(7, 2, 0)));
+        assert!(matches(&cron, at(8, 2, 0)));
+        assert!(!matches(&cron, at(8, 2, 1)));
+
+        let cron = Cron::parse("0 2 * * mon,wed").unwrap();
+        assert!(matches(&cron, at(2, 2, 0)));
+        assert!(!matches(&cron, at(3, 2, 0)));
+        assert!(matches(&cron, at(4, 2, 0)));
+
+        let cron = Cron::parse("0 2 * * MON-SUN").unwrap();
+        assert!((2..=8).all(|day| matches(&cron, at(day, 2, 0))));
+    }
+
+    #[test]
+    fn sunday_is_zero_and_seven() {
+        for expression in ["0 0 * * 0", "0 0 * * 7", "0 0 * * SUN"] {
+            let cron = Cron::parse(expression).unwrap();
+            assert!(matches(&cron, at(8, 0, 0)), "{}", expression);
+            assert!(!matches(&cron, at(7, 0, 0)), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn steps() {
+        let cron = Cron::parse("*/15 * * * *").unwrap();
+        assert!([0, 15, 30, 45].iter().all(|m| matches(&cron, at(2, 9, *m))));
+        assert!(!matches(&cron, at(2, 9, 10)));
+
+        let cron = Cron::parse("0-30/10 8 * * *").unwrap();
+        assert!([0, 10, 20, 30].iter().all(|m| matches(&cron, at(2, 8, *m))));
+        assert!(!matches(&cron, at(2, 8, 40)));
+
+        let cron = Cron::parse("5/20 * * * *").unwrap();
+        assert!([5, 25, 45].iter().all(|m| matches(&cron, at(2, 9, *m))));
+        assert!(!matches(&cron, at(2, 9, 0)));
+    }
+
+    #[test]
+    fn day_of_month_and_week_combine() {
+        // Both restricted: either one matches.
+        let cron = Cron::parse("0 0 1 * MON").unwrap();
+        assert!(matches(&cron, at(1, 0, 0)));
+        assert!(matches(&cron, at(2, 0, 0)));
+        assert!(!matches(&cron, at(3, 0, 0)));
+
+        // Only one restricted: it alone decides.
+        let cron = Cron::parse("0 0 1 * *").unwrap();
+        assert!(matches(&cron, at(1, 0, 0)));
+        assert!(!matches(&cron, at(2, 0, 0)));
+        let cron = Cron::parse("0 0 * * MON").unwrap();
+        assert!(!matches(&cron, at(1, 0, 0)));
+        assert!(matches(&cron, at(2, 0, 0)));
+    }
+
+    #[test]
+    fn month_names() {
+        let cron = Cron::parse("0 0 * JUN-AUG *").unwrap();
+        assert!(matches(&cron, at(2, 0, 0)));
+        let cron = Cron::parse("0 0 * jan *").unwrap();
+        assert!(!matches(&cron, at(2, 0, 0)));
+    }
+
+    #[test]
+    fn latest_occurrence_in_a_range() {
+        let cron = Cron::parse("30 2,14 * * MON").unwrap();
+        // From Wednesday back to Monday's 14:30 run.
+        assert_eq!(cron.latest(at(2, 0, 0), at(4, 12, 0)), Some(at(2, 14, 30)));
+        assert_eq!(cron.latest(at(2, 0, 0), at(2, 14, 29)), Some(at(2, 2, 30)));
+        assert_eq!(cron.latest(at(2, 0, 0), at(2, 14, 30)), Some(at(2, 14, 30)));
+        assert_eq!(cron.latest(at(2, 2, 31), at(2, 14, 29)), None);
+        assert_eq!(cron.latest(at(3, 0, 0), at(8, 23, 59)), None);
+
+        let cron = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(cron.latest(at(2, 9, 0), at(2, 9, 44)), Some(at(2, 9, 30)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "30-10 * * * *",
+            "* * * * FOO",
+        ] {
+            assert!(Cron::parse(expression).is_err(), "{}", expression);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+pub mod cron;
+pub mod rrule;
+
+use crate::alert::routing::wildcard_matches;
+use crate::maintenance::cron::Cron;
+use crate::maintenance::rrule::RRule;
+use crate::models::maintenance::MaintenanceWindow;
+use chrono::{DateTime, Duration, DurationRound, FixedOffset, NaiveDateTime, Utc};
+
+/// Longest occurrence of a recurring window.
+const MAX_DURATION_MINUTES: u64 = 7 * 24 * 60;
+
+/// A validated maintenance window.
+pub struct Maintenance {
+    pub window: MaintenanceWindow,
+    offset: FixedOffset,
+    recurrence: Option<Recurrence>,
+}
+
+enum Recurrence {
+    Cron(Cron),
+    RRule(RRule),
+}
+
+impl Recurrence {
+    fn latest(&self, from: NaiveDateTime, to: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Recurrence::Cron(cron) => cron.latest(from, to),
+            Recurrence::RRule(rrule) => rrule.latest(from, to),
+        }
+    }
+}
+
+impl Maintenance {
+    pub fn new(window: MaintenanceWindow) -> anyhow::Result<Self> {
+        let offset: FixedOffset = window.utc_offset.parse().map_err(|_| {
+            anyhow::anyhow!(
+                "invalid utc_offset '{}' (use e.g. +02:00)",
+                window.utc_offset
+            )
+        })?;
+        if window.services.is_empty() && window.tags.is_empty() {
+            anyhow::bail!("needs services or tags");
+        }
+        if let (Some(start), Some(end)) = (window.start, window.end) {
+            if end <= start {
+                anyhow::bail!("end must be after start");
+            }
+        }
+        if window
+            .duration_minutes
+            .is_some_and(|d| d == 0 || d > MAX_DURATION_MINUTES)
+        {
+            anyhow::bail!(
+                "duration_minutes must be between 1 and {}",
+                MAX_DURATION_MINUTES
+            );
+        }
+
+        let recurrence = match (&window.cron, &window.rrule) {
+            (Some(_), Some(_)) => anyhow::bail!("cannot have both cron and rrule"),
+            (Some(cron), None) => Some(Recurrence::Cron(Cron::parse(cron)?)),
+            (None, Some(rule)) => {
+                let start = window
+                    .start
+                    .ok_or_else(|| anyhow::anyhow!("rrule needs a start"))?;
+                let start = start.with_timezone(&offset).naive_local();
+                Some(Recurrence::RRule(RRule::parse(rule, start, offset)?))
+            }
+            (None, None) => {
+                if window.start.is_none() {
+                    anyhow::bail!("needs a start");
+                }
+                if window.end.is_none() && window.duration_minutes.is_none() {
+                    anyhow::bail!("needs an end or duration_minutes");
+                }
+                None
+            }
+        };
+        if recurrence.is_some() && window.duration_minutes.is_none() {
+            anyhow::bail!("recurring windows need duration_minutes");
+        }
+
+        Ok(Self {
+            window,
+            offset,
+            recurrence,
+        })
+    }
+
+    pub fn applies_to(&self, service: &str, tags: &[String]) -> bool {
+        self.window
+            .services
+            .iter()
+            .any(|p| wildcard_matches(p, service))
+            || self.window.tags.iter().any(|t| tags.contains(t))
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let duration = Duration::minutes(self.window.duration_minutes.unwrap_or_default() as i64);
+        let Some(recurrence) = &self.recurrence else {
+            let start = self.window.start.unwrap_or(now);
+            let end = self.window.end.unwrap_or(start + duration);
+            return start <= now && now < end;
+        };
+
+        // Look for an occurrence that started within the last `duration`,
+        // at or after `start` and before `end`.
+        let minute = |at: DateTime<Utc>| {
+            at.with_timezone(&self.offset)
+                .naive_local()
+                .duration_trunc(Duration::minutes(1))
+                .ok()
+        };
+        let Some(now_minute) = minute(now) else {
+            return false;
+        };
+        let mut from = now_minute - duration + Duration::minutes(1);
+        // Occurrences are whole minutes, so the first one may begin before a
+        // `start` with seconds.
+        if let Some(first) = self.window.start.and_then(minute) {
+            from = from.max(first);
+        }
+        let mut to = now_minute;
+        if let Some(end) = self.window.end {
+            let Some(last) = minute(end - Duration::nanoseconds(1)) else {
+                return false;
+            };
+            to = to.min(last);
+        }
+        from <= to && recurrence.latest(from, to).is_some()
+    }
+
+    /// When the window is over for good; `None` for open-ended series.
+    pub fn ends_at(&self) -> Option<DateTime<Utc>> {
+        let duration = Duration::minutes(self.window.duration_minutes.unwrap_or_default() as i64);
+        match self.recurrence {
+            None => self
+                .window
+                .end
+                .or_else(|| self.window.start.map(|start| start + duration)),
+            Some(_) => self.window.end.map(|end| end + duration),
+        }
+    }
+}
+
+/// Maintenance windows from the config file and those created through the
+/// API.
+#[derive(Default)]
+pub struct MaintenanceSchedule {
+    windows: Vec<Maintenance>,
+    last_id: u64,
+}
+
+impl MaintenanceSchedule {
+    pub fn new(windows: Vec<Maintenance>) -> Self {
+        let last_id = windows
+            .iter()
+            .filter_map(|m| m.window.id)
+            .max()
+            .unwrap_or_default();
+        Self { windows, last_id }
+    }
+
+    /// Validates `window` and adds it with a new id.
+    pub fn add(&mut self, mut window: MaintenanceWindow) -> anyhow::Result<MaintenanceWindow> {
+        window.id = Some(self.last_id + 1);
+        let maintenance = Maintenance::new(window)?;
+        self.last_id += 1;
+        let window = maintenance.window.clone();
+        self.windows.push(maintenance);
+        Ok(window)
+    }
+
+    /// Removes the window created through the API with `id`.
+    pub fn remove(&mut self, id: u64) -> Option<MaintenanceWindow> {
+        let index = self.windows.iter().position(|m| m.window.id == Some(id))?;
+        Some(self.windows.remove(index).window)
+    }
+
+    /// Replaces the windows from the config, keeping those created through
+    /// the API.
+    pub fn replace_configured(&mut self, windows: Vec<Maintenance>) {
+        self.windows.retain(|m| m.window.id.is_some());
+        self.windows.extend(windows);
+    }
+
+    /// Drops windows that ended before `cutoff`.
+    pub fn prune(&mut self, cutoff: DateTime<Utc>) {
+        self.windows
+            .retain(|m| m.ends_at().is_none_or(|end| end >= cutoff));
+    }
+
+    pub fn windows(&self) -> &[Maintenance] {
+        &self.windows
+    }
+
+    /// The first window covering `service` at `now`.
+    pub fn active(
+        &self,
+        service: &str,
+        tags: &[String],
+        now: DateTime<Utc>,
+    ) -> Option<&Maintenance> {
+        self.windows
+            .iter()
+            .find(|m| m.applies_to(service, tags) && m.is_active(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, day, hour, minute, 0).unwrap()
+    }
+
+    fn window(cron: &str, duration_minutes: u64) -> MaintenanceWindow {
+        serde_json::from_value(serde_json::json!({
+            "services": ["api"],
+            "cron": cron,
+            "duration_minutes": duration_minutes
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn recurring_window_covers_each_occurrence() {
+        // Saturday 23:30 for two days, evaluated at +02:00.
+        let mut weekend = window("30 23 * * SAT", 2 * 24 * 60);
+        weekend.utc_offset = "+02:00".to_string();
+        let weekend = Maintenance::new(weekend).unwrap();
+        assert!(!weekend.is_active(at(7, 21, 29)));
+        assert!(weekend.is_active(at(7, 21, 30)));
+        assert!(weekend.is_active(at(9, 21, 29)));
+        assert!(!weekend.is_active(at(9, 21, 30)));
+    }
+
+    #[test]
+    fn recurring_window_respects_start_and_end() {
+        let mut hourly = window("0 * * * *", 30);
+        hourly.start = Some(at(2, 10, 0) + Duration::seconds(20));
+        hourly.end = Some(at(2, 12, 0));
+        let hourly = Maintenance::new(hourly).unwrap();
+        assert!(!hourly.is_active(at(2, 9, 10)));
+        assert!(hourly.is_active(at(2, 10, 10)));
+        assert!(hourly.is_active(at(2, 11, 29)));
+        assert!(!hourly.is_active(at(2, 11, 30)));
+        // The 12:00 occurrence starts at the end of the series.
+        assert!(!hourly.is_active(at(2, 12, 10)));
+        // Occurrences that started before the end run their full duration.
+        let mut late = window("50 11 * * *", 30);
+        late.end = Some(at(2, 12, 0));
+        assert!(Maintenance::new(late).unwrap().is_active(at(2, 12, 10)));
+    }
+}
@@ -0,0 +1,335 @@
+use chrono::{Datelike, Duration, DurationRound, FixedOffset, NaiveDate, NaiveDateTime, Weekday};
+
+/// Subset of an iCalendar RRULE (RFC 5545) anchored at a local start time.
+///
+/// Supports `FREQ=DAILY|WEEKLY|MONTHLY`, `INTERVAL`, `BYDAY` (plain weekdays
+/// such as `MO,WE`), `BYMONTHDAY`, `UNTIL` and `COUNT`. Occurrences start at
+/// the time of day of `start`, to the minute.
+pub struct RRule {
+    start: NaiveDateTime,
+    frequency: Frequency,
+    interval: u32,
+    by_day: Vec<Weekday>,
+    by_month_day: Vec<u32>,
+    until: Option<NaiveDateTime>,
+    /// Day of the last occurrence allowed by `COUNT`.
+    last_day: Option<NaiveDate>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl RRule {
+    /// Parses `rule`, with `start` as its DTSTART; both are in local time at
+    /// `offset`, except an `UNTIL` ending in `Z`, which is UTC.
+    pub fn parse(rule: &str, start: NaiveDateTime, offset: FixedOffset) -> anyhow::Result<Self> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        // Occurrences are matched minute by minute.
+        let start = start.duration_trunc(Duration::minutes(1))?;
+
+        let mut frequency = None;
+        let mut count = None;
+        let mut parsed = Self {
+            start,
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            until: None,
+            last_day: None,
+        };
+
+        for part in rule.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid RRULE part '{}'", part))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        other => anyhow::bail!("unsupported RRULE FREQ '{}'", other),
+                    })
+                }
+                "INTERVAL" => {
+                    parsed.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or_else(|| anyhow::anyhow!("invalid RRULE INTERVAL '{}'", value))?
+                }
+                "BYDAY" => {
+                    parsed.by_day = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<anyhow::Result<_>>()?
+                }
+                "BYMONTHDAY" => {
+                    parsed.by_month_day = value
+                        .split(',')
+                        .map(|d| {
+                            d.parse()
+                                .ok()
+                                .filter(|d| (1..=31).contains(d))
+                                .ok_or_else(|| anyhow::anyhow!("invalid RRULE BYMONTHDAY '{}'", d))
+                        })
+                        .collect::<anyhow::Result<_>>()?
+                }
+                "UNTIL" => parsed.until = Some(parse_until(value, offset)?),
+                "COUNT" => {
+                    count =
+                        Some(
+                            value.parse().ok().filter(|c| *c > 0).ok_or_else(|| {
+                                anyhow::anyhow!("invalid RRULE COUNT '{}'", value)
+                            })?,
+                        )
+                }
+                other => anyhow::bail!("unsupported RRULE part '{}'", other),
+            }
+        }
+
+        parsed.frequency = frequency.ok_or_else(|| anyhow::anyhow!("RRULE needs a FREQ"))?;
+        parsed.last_day = count.and_then(|count| parsed.last_day(count));
+        Ok(parsed)
+    }
+
+    /// Whether an occurrence starts at minute `at`.
+    pub fn matches(&self, at: NaiveDateTime) -> bool {
+        at >= self.start
+            && at.time() == self.start.time()
+            && self.until.is_none_or(|until| at <= until)
+            && self.last_day.is_none_or(|last| at.date() <= last)
+            && self.date_matches(at.date())
+    }
+
+    /// The latest occurrence from `from` through `to`, both whole minutes.
+    pub fn latest(&self, from: NaiveDateTime, to: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut day = to.date();
+        while day >= from.date() {
+            let at = day.and_time(self.start.time());
+            if at >= from && at <= to && self.matches(at) {
+                return Some(at);
+            }
+            day = day.pred_opt()?;
+        }
+        None
+    }
+
+    /// Day of the `count`th occurrence, or `None` if `UNTIL` ends the series
+    /// first. Occurrences are at most daily and the supported rules match at
+    /// least once a year per interval, which bounds the scan.
+    fn last_day(&self, count: u32) -> Option<NaiveDate> {
+        let years = count as i64 * self.interval as i64;
+        let limit = Duration::try_days(years * 366)
+            .and_then(|span| self.start.date().checked_add_signed(span))
+            .unwrap_or(NaiveDate::MAX);
+
+        let mut seen = 0;
+        let mut day = self.start.date();
+        while day <= limit && self.until.is_none_or(|until| day <= until.date()) {
+            if self.date_matches(day) {
+                seen += 1;
+                if seen == count {
+                    return Some(day);
+                }
+            }
+            day = day.succ_opt()?;
+        }
+        None
+    }
+
+    fn date_matches(&self, date: NaiveDate) -> bool {
+        let start = self.start.date();
+        let by_day = self.by_day.is_empty() || self.by_day.contains(&date.weekday());
+        let by_month_day = self.by_month_day.is_empty() || self.by_month_day.contains(&date.day());
+
+        match self.frequency {
+            Frequency::Daily => {
+                (date - start).num_days() % self.interval as i64 == 0 && by_day && by_month_day
+            }
+            Frequency::Weekly => {
+                let week = |d: NaiveDate| d.week(Weekday::Mon).first_day();
+                let weeks = (week(date) - week(start)).num_days() / 7;
+                let day = if self.by_day.is_empty() {
+                    date.weekday() == start.weekday()
+                } else {
+                    by_day
+                };
+                weeks % self.interval as i64 == 0 && day && by_month_day
+            }
+            Frequency::Monthly => {
+                let months =
+                    (date.year() - start.year()) * 12 + date.month() as i32 - start.month() as i32;
+                let day = if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    date.day() == start.day()
+                } else {
+                    by_day && by_month_day
+                };
+                months % self.interval as i32 == 0 && day
+            }
+        }
+    }
+}
+
+fn parse_weekday(raw: &str) -> anyhow::Result<Weekday> {
+    Ok(match raw.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => anyhow::bail!("unsupported RRULE BYDAY '{}'", raw),
+    })
+}
+
+/// Local time of `UNTIL`, given as a date (`20250101`, inclusive) or a
+/// date-time (`20250101T000000`, or `20250101T000000Z` in UTC).
+fn parse_until(raw: &str, offset: FixedOffset) -> anyhow::Result<NaiveDateTime> {
+    if let Some(utc) = raw.strip_suffix('Z') {
+        let until = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map_err(|_| anyhow::anyhow!("invalid RRULE UNTIL '{}'", raw))?;
+        return Ok(until + Duration::seconds(offset.local_minus_utc() as i64));
+    }
+
+    NaiveDateTime::parse_from_str(raw, "%Y%m%dT%H%M%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(raw, "%Y%m%d").map(|d| d.and_hms_opt(23, 59, 59).unwrap())
+        })
+        .map_err(|_| anyhow::anyhow!("invalid RRULE UNTIL '{}'", raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    /// 2025-06-02 is a Monday.
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn parse(rule: &str) -> RRule {
+        RRule::parse(rule, at(6, 2, 22, 0), utc()).unwrap()
+    }
+
+    #[test]
+    fn daily_with_interval() {
+        let rule = parse("FREQ=DAILY;INTERVAL=2");
+        assert!(rule.matches(at(6, 2, 22, 0)));
+        assert!(!rule.matches(at(6, 3, 22, 0)));
+        assert!(rule.matches(at(6, 4, 22, 0)));
+        assert!(!rule.matches(at(6, 4, 22, 1)));
+        assert!(!rule.matches(at(6, 1, 22, 0)));
+    }
+
+    #[test]
+    fn weekly_by_day_with_interval() {
+        let rule = parse("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR");
+        assert!(rule.matches(at(6, 2, 22, 0)));
+        assert!(rule.matches(at(6, 6, 22, 0)));
+        assert!(!rule.matches(at(6, 9, 22, 0)));
+        assert!(!rule.matches(at(6, 13, 22, 0)));
+        assert!(rule.matches(at(6, 16, 22, 0)));
+    }
+
+    #[test]
+    fn weekly_defaults_to_start_weekday() {
+        let rule = parse("FREQ=WEEKLY");
+        assert!(rule.matches(at(6, 9, 22, 0)));
+        assert!(!rule.matches(at(6, 10, 22, 0)));
+    }
+
+    #[test]
+    fn monthly_by_month_day() {
+        let rule = parse("FREQ=MONTHLY;BYMONTHDAY=1,15");
+        assert!(rule.matches(at(6, 15, 22, 0)));
+        assert!(rule.matches(at(7, 1, 22, 0)));
+        assert!(!rule.matches(at(7, 2, 22, 0)));
+
+        let rule = parse("FREQ=MONTHLY;INTERVAL=2");
+        assert!(!rule.matches(at(7, 2, 22, 0)));
+        assert!(rule.matches(at(8, 2, 22, 0)));
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        let rule = parse("FREQ=DAILY;UNTIL=20250605");
+        assert!(rule.matches(at(6, 5, 22, 0)));
+        assert!(!rule.matches(at(6, 6, 22, 0)));
+
+        // 21:30 UTC is 23:30 at +02:00, after the 22:00 occurrence.
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let rule =
+            RRule::parse("FREQ=DAILY;UNTIL=20250605T213000Z", at(6, 2, 22, 0), offset).unwrap();
+        assert!(rule.matches(at(6, 5, 22, 0)));
+        assert!(!rule.matches(at(6, 6, 22, 0)));
+    }
+
+    #[test]
+    fn count_limits_occurrences() {
+        let rule = parse("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=3");
+        assert!(rule.matches(at(6, 2, 22, 0)));
+        assert!(rule.matches(at(6, 4, 22, 0)));
+        assert!(rule.matches(at(6, 9, 22, 0)));
+        assert!(!rule.matches(at(6, 11, 22, 0)));
+    }
+
+    #[test]
+    fn latest_occurrence_in_a_range() {
+        let rule = parse("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=3");
+        assert_eq!(
+            rule.latest(at(6, 1, 0, 0), at(6, 6, 0, 0)),
+            Some(at(6, 4, 22, 0))
+        );
+        assert_eq!(
+            rule.latest(at(6, 1, 0, 0), at(6, 4, 21, 59)),
+            Some(at(6, 2, 22, 0))
+        );
+        assert_eq!(rule.latest(at(6, 3, 0, 0), at(6, 4, 21, 59)), None);
+        // The fourth occurrence is past COUNT.
+        assert_eq!(rule.latest(at(6, 10, 0, 0), at(6, 12, 0, 0)), None);
+    }
+
+    #[test]
+    fn start_seconds_are_ignored() {
+        let start = at(6, 2, 22, 0) + Duration::seconds(30);
+        let rule = RRule::parse("FREQ=DAILY", start, utc()).unwrap();
+        assert!(rule.matches(at(6, 2, 22, 0)));
+        assert!(rule.matches(at(6, 3, 22, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for invalid in [
+            "INTERVAL=2",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;BYDAY=1MO",
+            "FREQ=DAILY;BYMONTHDAY=32",
+            "FREQ=DAILY;UNTIL=tomorrow",
+            "FREQ=DAILY;BYHOUR=2",
+        ] {
+            assert!(
+                RRule::parse(invalid, at(6, 2, 22, 0), utc()).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Regular expression matched against an HTTP response body.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BodyRegex(Regex);
+
+impl BodyRegex {
+    pub fn is_match(&self, body: &str) -> bool {
+        self.0.is_match(body)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl TryFrom<String> for BodyRegex {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        Regex::new(&raw)
+            .map(BodyRegex)
+            .map_err(|e| format!("invalid body_regex '{}': {}", raw, e))
+    }
+}
+
+impl From<BodyRegex> for String {
+    fn from(regex: BodyRegex) -> Self {
+        regex.0.as_str().to_string()
+    }
+}
+
+/// Assertion on a value inside a JSON response body.
+///
+/// Written as `<path>`, `<path> == <value>` or `<path> != <value>`, where the
+/// path is a small JSONPath subset (`$.a.b[0].c`) and the value is a JSON
+/// literal (`"ok"`, `true`, `3`). A bare word is compared as a string. A path
+/// without an operator only asserts that the value exists.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JsonPathAssertion {
+    spec: String,
+    path: Vec<PathSegment>,
+    op: Option<(CompareOp, Value)>,
+}
+
+#[derive(Clone, Debug)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CompareOp {
+    Eq,
+    Ne,
+}
+
+impl JsonPathAssertion {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (path_str, op) = match find_operator(spec) {
+            Some((at, op)) => (&spec[..at], Some((op, parse_literal(&spec[at + 2..])))),
+            None => (spec, None),
+        };
+
+        let path = parse_path(path_str.trim())
+            .map_err(|e| format!("invalid json_path '{}': {}", spec, e))?;
+
+        Ok(Self {
+            spec: spec.to_string(),
+            path,
+            op,
+        })
+    }
+
+    /// Evaluates the assertion against a raw response body.
+    pub fn evaluate(&self, body: &str) -> Result<(), String> {
+        let root: Value =
+            serde_json::from_str(body).map_err(|e| format!("body is not valid JSON: {}", e))?;
+
+        let mut current = &root;
+        for segment in &self.path {
+            let next = match segment {
+                PathSegment::Key(key) => current.get(key),
+                PathSegment::Index(index) => current.get(*index),
+            };
+            current = next.ok_or_else(|| format!("{} not found", self.path_str()))?;
+        }
+
+        match &self.op {
+            None => Ok(()),
+            Some((CompareOp::Eq, expected)) if current == expected => Ok(()),
+            Some((CompareOp::Ne, expected)) if current != expected => Ok(()),
+            Some(_) => Err(format!("{} is {}", self.spec.trim(), current)),
+        }
+    }
+
+    fn path_str(&self) -> &str {
+        let end = find_operator(&self.spec).map_or(self.spec.len(), |(at, _)| at);
+        self.spec[..end].trim()
+    }
+}
+
+impl TryFrom<String> for JsonPathAssertion {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        Self::parse(&raw)
+    }
+}
+
+impl From<JsonPathAssertion> for String {
+    fn from(assertion: JsonPathAssertion) -> Self {
+        assertion.spec
+    }
+}
+
+/// Position of the first `==` or `!=`; the value after it may contain either.
+fn find_operator(spec: &str) -> Option<(usize, CompareOp)> {
+    let eq = spec.find("==").map(|at| (at, CompareOp::Eq));
+    let ne = spec.find("!=").map(|at| (at, CompareOp::Ne));
+    [eq, ne].into_iter().flatten().min_by_key(|(at, _)| *at)
+}
+
+fn parse_literal(raw: &str) -> Value {
+    let raw = raw.trim();
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+fn parse_path(raw: &str) -> Result<Vec<PathSegment>, String> {
+    let rest = raw
+        .strip_prefix('$')
+        .ok_or_else(|| "path must start with '$'".to_string())?;
+
+    let mut segments = Vec::new();
+    let mut chars = rest.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next == '.' || next == '[' {
+                        break;
+                    }
+                    key.push(next);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return Err("empty key".to_string());
+                }
+                segments.push(PathSegment::Key(key));
+            }
+            '[' => {
+                let mut inner = String::new();
+                for next in chars.by_ref() {
+                    if next == ']' {
+                        break;
+                    }
+                    inner.push(next);
+                }
+                let inner = inner.trim();
+                if let Some(key) = inner
+                    .strip_prefix('"')
+                    .and_then(|k| k.strip_suffix('"'))
+                    .or_else(|| inner.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')))
+                {
+                    segments.push(PathSegment::Key(key.to_string()));
+                } else {
+                    let index = inner
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid index '{}'", inner))?;
+                    segments.push(PathSegment::Index(index));
+                }
+            }
+            other => return Err(format!("unexpected '{}'", other)),
+        }
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(spec: &str, body: &str) -> Result<(), String> {
+        JsonPathAssertion::parse(spec).unwrap().evaluate(body)
+    }
+
+    #[test]
+    fn paths() {
+        let body = r#"{"a": {"b": [{"c": 1}, {"c": 2}]}, "odd key": true}"#;
+        assert!(evaluate("$.a.b[1].c == 2", body).is_ok());
+        assert!(evaluate("$['odd key']", body).is_ok());
+        assert!(evaluate(r#"$["a"].b[0]"#, body).is_ok());
+        assert_eq!(
+            evaluate("$.a.b[2]", body),
+            Err("$.a.b[2] not found".to_string())
+        );
+        assert!(evaluate("$.a", "not json").is_err());
+    }
+
+    #[test]
+    fn comparisons() {
+        let body = r#"{"status": "ok", "count": 3, "up": true}"#;
+        assert!(evaluate(r#"$.status == "ok""#, body).is_ok());
+        assert!(evaluate("$.status == ok", body).is_ok());
+        assert!(evaluate("$.count == 3", body).is_ok());
+        assert!(evaluate("$.up != false", body).is_ok());
+        assert_eq!(
+            evaluate("$.count != 3", body),
+            Err("$.count != 3 is 3".to_string())
+        );
+    }
+
+    #[test]
+    fn first_operator_wins() {
+        let body = r#"{"v": "x"}"#;
+        assert!(evaluate(r#"$.v != "a==b""#, body).is_ok());
+        assert!(evaluate(r#"$.v == "a!=b""#, body).is_err());
+        assert!(evaluate(r#"$.v == "a!=b""#, r#"{"v": "a!=b"}"#).is_ok());
+        assert_eq!(
+            evaluate(r#"$.w != "a==b""#, body),
+            Err("$.w not found".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for spec in ["a.b", "$.", "$.a[x]", "$a", "$.a[1] x"] {
+            assert!(JsonPathAssertion::parse(spec).is_err(), "{}", spec);
+        }
+    }
+}
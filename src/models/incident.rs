@@ -1,9 +1,114 @@
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[allow(dead_code)]
+/// Messages kept per incident; later ones are dropped.
+const MAX_MESSAGES: usize = 100;
+
+/// A period during which a service was `Down`, `Flapping` or `Degraded`.
+///
+/// Opened when the service status becomes one of these and closed when it
+/// recovers. While open, every failing check is added to `failing_checks`
+/// and each new failure message is appended to `messages`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Incident {
+    pub id: u64,
     pub service_name: String,
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
+    pub duration_seconds: Option<i64>,
+    pub failing_checks: Vec<String>,
+    pub message: String,
+    pub messages: Vec<IncidentMessage>,
+    /// Notifiers alerted when the incident opened or escalated; its recovery
+    /// goes to them too.
+    #[serde(default)]
+    pub notified: Vec<String>,
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub acknowledged_by: Option<String>,
+    /// Escalation tiers already alerted.
+    #[serde(default)]
+    pub escalation_level: usize,
+    /// Opened for a degraded service; cleared if the service goes down.
+    #[serde(default)]
+    pub degraded: bool,
+    /// When a degraded incident's service went down; escalation counts from
+    /// here instead of `started_at`.
+    #[serde(default)]
+    pub down_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IncidentMessage {
+    pub timestamp: DateTime<Utc>,
+    pub check_type: String,
     pub message: String,
 }
+
+impl Incident {
+    pub fn open(id: u64, service_name: String, started_at: DateTime<Utc>, message: String) -> Self {
+        Self {
+            id,
+            service_name,
+            started_at,
+            ended_at: None,
+            duration_seconds: None,
+            failing_checks: Vec::new(),
+            message,
+            messages: Vec::new(),
+            notified: Vec::new(),
+            acknowledged_at: None,
+            acknowledged_by: None,
+            escalation_level: 0,
+            degraded: false,
+            down_at: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.ended_at.is_none()
+    }
+
+    /// Records a failing check; repeated identical messages are only kept once.
+    pub fn record_failure(&mut self, at: DateTime<Utc>, check_type: &str, message: &str) {
+        if !self.failing_checks.iter().any(|c| c == check_type) {
+            self.failing_checks.push(check_type.to_string());
+        }
+
+        let repeated = self
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.check_type == check_type)
+            .is_some_and(|m| m.message == message);
+        if !repeated && self.messages.len() < MAX_MESSAGES {
+            self.messages.push(IncidentMessage {
+                timestamp: at,
+                check_type: check_type.to_string(),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    /// When the service went down, which escalation delays count from.
+    pub fn down_since(&self) -> DateTime<Utc> {
+        self.down_at.unwrap_or(self.started_at)
+    }
+
+    pub fn acknowledge(&mut self, at: DateTime<Utc>, by: Option<String>) {
+        self.acknowledged_at = Some(at);
+        self.acknowledged_by = by;
+    }
+
+    pub fn close(&mut self, at: DateTime<Utc>) {
+        self.ended_at = Some(at);
+        self.duration_seconds = Some((at - self.started_at).num_seconds());
+    }
+
+    /// Whether the incident overlaps the range between `since` and `until`.
+    pub fn overlaps(&self, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> bool {
+        until.is_none_or(|until| self.started_at <= until)
+            && since.is_none_or(|since| self.ended_at.is_none_or(|ended| ended >= since))
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A period during which checks keep running but alerts are suppressed and
+/// uptime is not counted.
+///
+/// Without `cron` or `rrule` it is a one-off window from `start` to `end` (or
+/// `start` plus `duration_minutes`). With either, every occurrence lasts
+/// `duration_minutes` and `start` / `end` bound the series.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// Assigned to windows created through the API; `None` for windows from
+    /// the config file.
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub name: String,
+    /// Service name patterns; `*` matches any run of characters.
+    #[serde(default)]
+    pub services: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub duration_minutes: Option<u64>,
+    /// Five-field cron expression of occurrence starts, e.g. `0 2 * * SUN`.
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// iCalendar RRULE, e.g. `FREQ=WEEKLY;BYDAY=TU,TH`; occurrences start at
+    /// the time of day of `start`.
+    #[serde(default)]
+    pub rrule: Option<String>,
+    /// Offset `cron` and `rrule` are evaluated in, e.g. `+02:00`.
+    #[serde(default = "default_utc_offset")]
+    pub utc_offset: String,
+}
+
+pub(crate) fn default_utc_offset() -> String {
+    "+00:00".to_string()
+}
@@ -1,2 +1,5 @@
+pub mod assertion;
 pub mod incident;
+pub mod maintenance;
 pub mod service;
+pub mod window;
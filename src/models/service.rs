@@ -1,4 +1,6 @@
+use crate::models::assertion::{BodyRegex, JsonPathAssertion};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub enum CheckType {
@@ -8,11 +10,115 @@ pub enum CheckType {
     Ssl,
 }
 
+impl CheckType {
+    /// Name used for the check in state, history and the API.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckType::Http => "HTTP",
+            CheckType::Tcp => "TCP",
+            CheckType::Dns => "DNS",
+            CheckType::Ssl => "SSL",
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CheckConfig {
     pub check_type: CheckType,
     pub interval_seconds: u64,
     pub timeout_ms: u64,
+    #[serde(default = "default_threshold")]
+    pub failure_threshold: u32,
+    #[serde(default = "default_threshold")]
+    pub recovery_threshold: u32,
+    #[serde(default = "default_flap_detection")]
+    pub flap_detection: bool,
+    /// Number of recent results flap detection looks at.
+    #[serde(default = "default_flap_history")]
+    pub flap_history: usize,
+    /// State change percentage below which a flapping check settles.
+    #[serde(default = "default_flap_low_threshold")]
+    pub flap_low_threshold: f64,
+    /// State change percentage at which a check starts flapping.
+    #[serde(default = "default_flap_high_threshold")]
+    pub flap_high_threshold: f64,
+    /// Response time at or above which a working check is `Degraded`.
+    #[serde(default)]
+    pub response_time_warning_ms: Option<u64>,
+    /// Response time at or above which a check is `Down`.
+    #[serde(default)]
+    pub response_time_critical_ms: Option<u64>,
+
+    // Http only
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub expected_status: Option<ExpectedStatus>,
+    #[serde(default = "default_follow_redirects")]
+    pub follow_redirects: bool,
+    #[serde(default)]
+    pub http_client: Option<String>,
+    #[serde(default)]
+    pub body_contains: Option<String>,
+    #[serde(default)]
+    pub body_not_contains: Option<String>,
+    #[serde(default)]
+    pub body_regex: Option<BodyRegex>,
+    #[serde(default)]
+    pub json_path: Option<JsonPathAssertion>,
+
+    // Ssl only
+    #[serde(default = "default_expiry_warning_days")]
+    pub expiry_warning_days: i64,
+
+    // Dns only
+    #[serde(default)]
+    pub record_type: Option<DnsRecordType>,
+    #[serde(default)]
+    pub nameservers: Vec<String>,
+    #[serde(default)]
+    pub expected_values: Vec<String>,
+    #[serde(default)]
+    pub expected_match: DnsMatch,
+    #[serde(default = "default_attempts")]
+    pub attempts: usize,
+}
+
+impl CheckConfig {
+    pub fn has_body_assertions(&self) -> bool {
+        self.body_contains.is_some()
+            || self.body_not_contains.is_some()
+            || self.body_regex.is_some()
+            || self.json_path.is_some()
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum DnsRecordType {
+    A,
+    AAAA,
+    CNAME,
+    MX,
+    TXT,
+    NS,
+    SOA,
+    SRV,
+    CAA,
+}
+
+/// How `expected_values` are compared with the records returned by a `Dns` check.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum DnsMatch {
+    /// The answer set must equal the expected set.
+    #[default]
+    Exact,
+    /// Every expected value must be present; extra records are allowed.
+    Contains,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -20,4 +126,199 @@ pub struct Service {
     pub name: String,
     pub url: String,
     pub checks: Vec<CheckConfig>,
+    /// Names of entries in the top-level `notifiers` to alert.
+    #[serde(default)]
+    pub notifiers: Vec<String>,
+    /// Labels matched by `alert_rules`, e.g. `prod`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Name of an entry in the top-level `escalation_policies`.
+    #[serde(default)]
+    pub escalation_policy: Option<String>,
+    /// Names of services this one needs; while one of them is down this
+    /// service is `Impacted` instead of `Down` and does not alert.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// How urgent alerts for a service are; matched by `alert_rules`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Severity {
+    #[default]
+    Critical,
+    Warning,
+    Info,
+}
+
+fn default_threshold() -> u32 {
+    1
+}
+
+fn default_flap_detection() -> bool {
+    false
+}
+
+fn default_flap_history() -> usize {
+    21
+}
+
+fn default_flap_low_threshold() -> f64 {
+    25.0
+}
+
+fn default_flap_high_threshold() -> f64 {
+    50.0
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_follow_redirects() -> bool {
+    true
+}
+
+fn default_expiry_warning_days() -> i64 {
+    14
+}
+
+fn default_attempts() -> usize {
+    2
+}
+
+/// Set of HTTP status codes accepted as a successful `Http` check.
+///
+/// Accepts either a list of codes (`[200, 204]`) or a comma-separated spec
+/// string where each item is a code (`301`), a class (`2xx`) or an inclusive
+/// range (`200-299`), e.g. `"2xx,301"`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "RawExpectedStatus", into = "RawExpectedStatus")]
+pub struct ExpectedStatus {
+    spec: String,
+    ranges: Vec<(u16, u16)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawExpectedStatus {
+    Codes(Vec<u16>),
+    Spec(String),
+}
+
+impl ExpectedStatus {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let lower = part.to_ascii_lowercase();
+
+            let range = if let Some(class) = lower.strip_suffix("xx") {
+                let digit = class
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|d| (1..=5).contains(d))
+                    .ok_or_else(|| format!("invalid status class '{}'", part))?;
+                (digit * 100, digit * 100 + 99)
+            } else if let Some((from, to)) = lower.split_once('-') {
+                let from = parse_code(from.trim())?;
+                let to = parse_code(to.trim())?;
+                if from > to {
+                    return Err(format!("invalid status range '{}'", part));
+                }
+                (from, to)
+            } else {
+                let code = parse_code(&lower)?;
+                (code, code)
+            };
+
+            ranges.push(range);
+        }
+
+        if ranges.is_empty() {
+            return Err("expected_status must not be empty".to_string());
+        }
+
+        Ok(Self {
+            spec: spec.to_string(),
+            ranges,
+        })
+    }
+
+    pub fn matches(&self, code: u16) -> bool {
+        self.ranges
+            .iter()
+            .any(|(from, to)| (*from..=*to).contains(&code))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.spec
+    }
+}
+
+fn parse_code(raw: &str) -> Result<u16, String> {
+    raw.parse::<u16>()
+        .ok()
+        .filter(|c| (100..=599).contains(c))
+        .ok_or_else(|| format!("invalid status code '{}'", raw))
+}
+
+impl TryFrom<RawExpectedStatus> for ExpectedStatus {
+    type Error = String;
+
+    fn try_from(raw: RawExpectedStatus) -> Result<Self, Self::Error> {
+        match raw {
+            RawExpectedStatus::Codes(codes) => {
+                let spec: Vec<String> = codes.iter().map(|c| c.to_string()).collect();
+                Self::parse(&spec.join(","))
+            }
+            RawExpectedStatus::Spec(spec) => Self::parse(&spec),
+        }
+    }
+}
+
+impl From<ExpectedStatus> for RawExpectedStatus {
+    fn from(expected: ExpectedStatus) -> Self {
+        RawExpectedStatus::Spec(expected.spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_status_spec() {
+        let expected = ExpectedStatus::parse("2xx, 301,400-403").unwrap();
+        assert!([200, 250, 299, 301, 400, 403]
+            .iter()
+            .all(|c| expected.matches(*c)));
+        assert!([199, 300, 302, 404, 500]
+            .iter()
+            .all(|c| !expected.matches(*c)));
+        assert_eq!(expected.as_str(), "2xx, 301,400-403");
+    }
+
+    #[test]
+    fn expected_status_from_json() {
+        let expected: ExpectedStatus = serde_json::from_str("[200, 204]").unwrap();
+        assert!(expected.matches(204));
+        assert!(!expected.matches(201));
+        assert_eq!(expected.as_str(), "200,204");
+
+        let expected: ExpectedStatus = serde_json::from_str(r#""3XX""#).unwrap();
+        assert!(expected.matches(308));
+        assert_eq!(serde_json::to_string(&expected).unwrap(), r#""3XX""#);
+    }
+
+    #[test]
+    fn expected_status_rejects_invalid_specs() {
+        for spec in [
+            "", " , ", "6xx", "0xx", "2x", "99", "600", "abc", "300-200", "200-",
+        ] {
+            assert!(ExpectedStatus::parse(spec).is_err(), "{}", spec);
+        }
+        assert!(serde_json::from_str::<ExpectedStatus>("[]").is_err());
+    }
 }
@@ -0,0 +1,87 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// A rolling time window written as `<number><unit>`, e.g. `30m`, `24h`, `7d`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeWindow {
+    label: String,
+    duration: Duration,
+}
+
+impl TimeWindow {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let split = raw
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| format!("window '{}' is missing a unit (m, h, d)", raw))?;
+        let (value, unit) = raw.split_at(split);
+        let value: i64 = value
+            .parse()
+            .ok()
+            .filter(|v| *v > 0)
+            .ok_or_else(|| format!("invalid window '{}'", raw))?;
+
+        let duration = match unit {
+            "m" => Duration::try_minutes(value),
+            "h" => Duration::try_hours(value),
+            "d" => Duration::try_days(value),
+            _ => return Err(format!("invalid unit in window '{}' (use m, h, d)", raw)),
+        }
+        .ok_or_else(|| format!("window '{}' is too long", raw))?;
+
+        Ok(Self {
+            label: raw.to_string(),
+            duration,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+impl TryFrom<String> for TimeWindow {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        Self::parse(&raw)
+    }
+}
+
+impl From<TimeWindow> for String {
+    fn from(window: TimeWindow) -> Self {
+        window.label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(
+            TimeWindow::parse("30m").unwrap().duration(),
+            Duration::minutes(30)
+        );
+        assert_eq!(
+            TimeWindow::parse("24h").unwrap().duration(),
+            Duration::hours(24)
+        );
+        assert_eq!(TimeWindow::parse(" 7d ").unwrap().label(), "7d");
+    }
+
+    #[test]
+    fn rejects_invalid_windows() {
+        assert!(TimeWindow::parse("0h").is_err());
+        assert!(TimeWindow::parse("24").is_err());
+        assert!(TimeWindow::parse("3w").is_err());
+        assert!(TimeWindow::parse("200000000000000d").is_err());
+        assert!(TimeWindow::parse("99999999999999999999d").is_err());
+    }
+}
@@ -1,35 +1,72 @@
-use crate::models::service::{CheckConfig, Service};
-use crate::state::AppState;
+use crate::models::service::{CheckConfig, DnsMatch, DnsRecordType, Service};
+use crate::monitor::resolver::ResolverPool;
+use crate::state::{AppState, CheckResult};
 use crate::utils::net::normalize_host;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use trust_dns_resolver::proto::rr::RecordType;
 use trust_dns_resolver::TokioAsyncResolver;
 
-pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
+pub async fn run(
+    service: &Service,
+    check: &CheckConfig,
+    state: &AppState,
+    resolvers: &ResolverPool,
+) {
     let start = Instant::now();
-    let resolver = TokioAsyncResolver::tokio_from_system_conf().unwrap();
+    let resolver = match resolvers.get(check) {
+        Ok(resolver) => resolver,
+        Err(message) => {
+            println!("{} DNS FAILED: {}", service.name, message);
+
+            state
+                .update_check_status(
+                    service.name.clone(),
+                    service.url.clone(),
+                    "DNS".to_string(),
+                    CheckResult::down(None, message),
+                    check,
+                )
+                .await;
+            return;
+        }
+    };
     let host = normalize_host(service.url.as_str());
-    let result = resolver.lookup_ip(host.as_str()).await;
+    let lookup_future = lookup(&resolver, host.as_str(), check.record_type);
+    let result = timeout(Duration::from_millis(check.timeout_ms), lookup_future).await;
     let elapsed = start.elapsed().as_millis() as u64;
 
     match result {
-        Ok(response) => {
-            let ips: Vec<String> = response.iter().map(|ip| ip.to_string()).collect();
-            let message = format!("Resolved to: {}", ips.join(", "));
-            println!("{} DNS OK", service.name);
+        Ok(Ok(records)) => {
+            let label = check
+                .record_type
+                .map(|t| format!("{:?}", t))
+                .unwrap_or_else(|| "Resolved to".to_string());
+            let mut message = format!("{}: {}", label, records.join(", "));
+
+            let result = match compare_records(check, &records) {
+                Ok(()) => {
+                    println!("{} DNS OK", service.name);
+                    CheckResult::up(Some(elapsed), message)
+                }
+                Err(reason) => {
+                    message = format!("{} ({})", reason, message);
+                    println!("{} DNS FAILED: {}", service.name, message);
+                    CheckResult::down(Some(elapsed), message)
+                }
+            };
 
             state
                 .update_check_status(
                     service.name.clone(),
                     service.url.clone(),
                     "DNS".to_string(),
-                    true,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    result,
+                    check,
                 )
                 .await;
         }
-        Err(e) => {
+        Ok(Err(e)) => {
             let message = format!("Error: {}", e);
             println!("{} DNS FAILED: {}", service.name, e);
 
@@ -38,12 +75,275 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                     service.name.clone(),
                     service.url.clone(),
                     "DNS".to_string(),
-                    false,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::down(Some(elapsed), message),
+                    check,
+                )
+                .await;
+        }
+        Err(_) => {
+            let message = "Timed out".to_string();
+            println!("{} DNS TIMEOUT", service.name);
+
+            state
+                .update_check_status(
+                    service.name.clone(),
+                    service.url.clone(),
+                    "DNS".to_string(),
+                    CheckResult::down(Some(elapsed), message),
+                    check,
                 )
                 .await;
         }
     }
 }
+
+/// Resolves `host` and returns the answers rendered as zone-file style strings.
+///
+/// Without an explicit record type this is an A/AAAA lookup.
+async fn lookup(
+    resolver: &TokioAsyncResolver,
+    host: &str,
+    record_type: Option<DnsRecordType>,
+) -> Result<Vec<String>, trust_dns_resolver::error::ResolveError> {
+    let Some(record_type) = record_type else {
+        let response = resolver.lookup_ip(host).await?;
+        return Ok(response.iter().map(|ip| ip.to_string()).collect());
+    };
+
+    let record_type = to_record_type(record_type);
+    let response = resolver.lookup(host, record_type).await?;
+
+    // The answer may also contain the CNAME chain that led to the records.
+    Ok(response
+        .record_iter()
+        .filter(|r| r.record_type() == record_type)
+        .filter_map(|r| r.data())
+        .map(|data| data.to_string())
+        .collect())
+}
+
+fn to_record_type(record_type: DnsRecordType) -> RecordType {
+    match record_type {
+        DnsRecordType::A => RecordType::A,
+        DnsRecordType::AAAA => RecordType::AAAA,
+        DnsRecordType::CNAME => RecordType::CNAME,
+        DnsRecordType::MX => RecordType::MX,
+        DnsRecordType::TXT => RecordType::TXT,
+        DnsRecordType::NS => RecordType::NS,
+        DnsRecordType::SOA => RecordType::SOA,
+        DnsRecordType::SRV => RecordType::SRV,
+        DnsRecordType::CAA => RecordType::CAA,
+    }
+}
+
+fn compare_records(check: &CheckConfig, records: &[String]) -> Result<(), String> {
+    if check.expected_values.is_empty() {
+        return Ok(());
+    }
+
+    let case_sensitive = matches!(check.record_type, Some(DnsRecordType::TXT));
+    let actual: Vec<String> = records
+        .iter()
+        .map(|r| normalize_record(r, case_sensitive))
+        .collect();
+    let expected: Vec<String> = check
+        .expected_values
+        .iter()
+        .map(|r| normalize_record(r, case_sensitive))
+        .collect();
+
+    let missing: Vec<&String> = expected.iter().filter(|e| !actual.contains(e)).collect();
+    if !missing.is_empty() {
+        let missing: Vec<&str> = missing.iter().map(|m| m.as_str()).collect();
+        return Err(format!("missing expected: {}", missing.join(", ")));
+    }
+
+    if let DnsMatch::Exact = check.expected_match {
+        let unexpected: Vec<&str> = actual
+            .iter()
+            .filter(|a| !expected.contains(a))
+            .map(|a| a.as_str())
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(format!("unexpected records: {}", unexpected.join(", ")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Normalizes a record for comparison: collapses whitespace and drops the
+/// trailing `.` of fully-qualified names (`mail.example.com.` -> `mail.example.com`).
+fn normalize_record(raw: &str, case_sensitive: bool) -> String {
+    let normalized: Vec<&str> = raw
+        .split_whitespace()
+        .map(|part| part.strip_suffix('.').unwrap_or(part))
+        .collect();
+    let normalized = normalized.join(" ");
+
+    if case_sensitive {
+        normalized
+    } else {
+        normalized.to_ascii_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::state::HealthStatus;
+    use std::net::Ipv4Addr;
+    use tokio::net::UdpSocket;
+    use trust_dns_resolver::proto::op::{Message, MessageType};
+    use trust_dns_resolver::proto::rr::{rdata, RData, Record};
+
+    fn dns_check(record_type: &str, expected: &[&str], expected_match: &str) -> CheckConfig {
+        serde_json::from_value(serde_json::json!({
+            "check_type": "Dns",
+            "interval_seconds": 30,
+            "timeout_ms": 1000,
+            "record_type": record_type,
+            "expected_values": expected,
+            "expected_match": expected_match,
+        }))
+        .unwrap()
+    }
+
+    fn records(records: &[&str]) -> Vec<String> {
+        records.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn exact_match_needs_the_same_answer_set() {
+        let check = dns_check("A", &["192.0.2.1", "192.0.2.2"], "Exact");
+        assert!(compare_records(&check, &records(&["192.0.2.2", "192.0.2.1"])).is_ok());
+        assert_eq!(
+            compare_records(&check, &records(&["192.0.2.1"])),
+            Err("missing expected: 192.0.2.2".to_string())
+        );
+        assert_eq!(
+            compare_records(&check, &records(&["192.0.2.1", "192.0.2.2", "192.0.2.3"])),
+            Err("unexpected records: 192.0.2.3".to_string())
+        );
+    }
+
+    #[test]
+    fn contains_match_allows_extra_records() {
+        let check = dns_check("A", &["192.0.2.1"], "Contains");
+        assert!(compare_records(&check, &records(&["192.0.2.1", "192.0.2.3"])).is_ok());
+        assert!(compare_records(&check, &records(&["192.0.2.3"])).is_err());
+    }
+
+    #[test]
+    fn no_expected_values_accepts_any_answer() {
+        let check = dns_check("A", &[], "Exact");
+        assert!(compare_records(&check, &records(&["192.0.2.1"])).is_ok());
+    }
+
+    #[test]
+    fn names_match_with_or_without_trailing_dot() {
+        let check = dns_check("MX", &["10 Mail.Example.com"], "Exact");
+        assert!(compare_records(&check, &records(&["10 mail.example.com."])).is_ok());
+        assert_eq!(
+            normalize_record("10   mail.example.com.", false),
+            "10 mail.example.com"
+        );
+    }
+
+    #[test]
+    fn txt_records_are_case_sensitive() {
+        let check = dns_check("TXT", &["v=spf1 -all"], "Exact");
+        assert!(compare_records(&check, &records(&["v=spf1 -all"])).is_ok());
+        assert!(compare_records(&check, &records(&["V=SPF1 -ALL"])).is_err());
+    }
+
+    /// Answers every A query on a local UDP port with `answers`.
+    async fn start_nameserver(answers: Vec<Ipv4Addr>) -> std::net::SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buffer).await {
+                let Ok(query) = Message::from_vec(&buffer[..len]) else {
+                    continue;
+                };
+                let mut response = Message::new();
+                response
+                    .set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_desired(query.recursion_desired())
+                    .set_recursion_available(true);
+                for question in query.queries() {
+                    response.add_query(question.clone());
+                    for ip in &answers {
+                        response.add_answer(Record::from_rdata(
+                            question.name().clone(),
+                            60,
+                            RData::A(rdata::A(*ip)),
+                        ));
+                    }
+                }
+                let _ = socket.send_to(&response.to_vec().unwrap(), from).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn checks_against_a_local_nameserver() {
+        let nameserver = start_nameserver(vec![
+            Ipv4Addr::new(192, 0, 2, 1),
+            Ipv4Addr::new(192, 0, 2, 2),
+        ])
+        .await;
+
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [
+                {
+                    "name": "matching",
+                    "url": "https://app.example.test/health",
+                    "checks": [{
+                        "check_type": "Dns",
+                        "interval_seconds": 30,
+                        "timeout_ms": 2000,
+                        "record_type": "A",
+                        "nameservers": [nameserver.to_string()],
+                        "expected_values": ["192.0.2.1", "192.0.2.2"]
+                    }]
+                },
+                {
+                    "name": "changed",
+                    "url": "app.example.test",
+                    "checks": [{
+                        "check_type": "Dns",
+                        "interval_seconds": 30,
+                        "timeout_ms": 2000,
+                        "record_type": "A",
+                        "nameservers": [nameserver.to_string()],
+                        "expected_values": ["192.0.2.1"]
+                    }]
+                }
+            ]
+        }))
+        .unwrap();
+        let state = AppState::for_test("dns-check", &config);
+        let resolvers = ResolverPool::new();
+        for service in &config.services {
+            run(service, &service.checks[0], &state, &resolvers).await;
+        }
+
+        let services = state.services.read().await;
+        let matching = &services["matching"].checks[0];
+        assert_eq!(matching.status, HealthStatus::Up, "{}", matching.message);
+        assert_eq!(matching.message, "A: 192.0.2.1, 192.0.2.2");
+
+        let changed = &services["changed"].checks[0];
+        assert_eq!(changed.status, HealthStatus::Down);
+        assert!(
+            changed.message.starts_with("unexpected records: 192.0.2.2"),
+            "{}",
+            changed.message
+        );
+    }
+}
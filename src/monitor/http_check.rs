@@ -1,37 +1,115 @@
+use crate::config::HttpVersion;
 use crate::models::service::{CheckConfig, Service};
-use crate::state::AppState;
-use reqwest::Client;
-use std::time::Instant;
+use crate::monitor::http_client::HttpClientPool;
+use crate::monitor::http_timing::{PhaseRecorder, Phases};
+use crate::state::{AppState, CheckResult, HttpTimings};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, Version};
+use std::time::{Duration, Instant};
 
-pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
-    let client = Client::new();
+pub async fn run(
+    service: &Service,
+    check: &CheckConfig,
+    state: &AppState,
+    http_clients: &HttpClientPool,
+) {
     let start = Instant::now();
 
-    let result = client
-        .get(&service.url)
-        .timeout(std::time::Duration::from_millis(check.timeout_ms))
-        .send()
+    let request = http_clients
+        .get(check, &service.url)
+        .and_then(|client| build_request(&client, service, check));
+    let request = match request {
+        Ok(request) => request,
+        Err(message) => {
+            println!("{} FAILED: {}", service.name, message);
+
+            state
+                .update_check_status(
+                    service.name.clone(),
+                    service.url.clone(),
+                    "HTTP".to_string(),
+                    CheckResult::down(None, message),
+                    check,
+                )
+                .await;
+            return;
+        }
+    };
+
+    let recorder = PhaseRecorder::default();
+    let result = recorder
+        .scope(
+            request
+                .timeout(std::time::Duration::from_millis(check.timeout_ms))
+                .send(),
+        )
         .await;
 
-    let elapsed = start.elapsed().as_millis() as u64;
+    let headers_at = start.elapsed();
+    let elapsed = headers_at.as_millis() as u64;
 
     match result {
-        Ok(resp) => {
+        Ok(mut resp) => {
             let status_code = resp.status();
-            let is_success = status_code.is_success();
-            let message = format!("HTTP {}", status_code);
+            let status_ok = match &check.expected_status {
+                Some(expected) => expected.matches(status_code.as_u16()),
+                None => status_code.is_success(),
+            };
+            let version = resp.version();
+            let version_ok = !matches!(http_clients.http_version(check), HttpVersion::Http2)
+                || version == Version::HTTP_2;
+
+            let body = read_body(&mut resp).await;
+            let download = matches!(body, Ok((_, false))).then(|| start.elapsed() - headers_at);
+            let timings = http_timings(recorder.phases(), headers_at, download);
+
+            let (is_success, message) = if !status_ok {
+                let message = match &check.expected_status {
+                    Some(expected) => {
+                        format!("HTTP {} (expected {})", status_code, expected.as_str())
+                    }
+                    None => format!("HTTP {}", status_code),
+                };
+                (false, message)
+            } else if !version_ok {
+                (
+                    false,
+                    format!("HTTP {} over {:?}, expected HTTP/2", status_code, version),
+                )
+            } else if !check.has_body_assertions() {
+                // Only the download timing is lost if the body cannot be read.
+                (true, format!("HTTP {}", status_code))
+            } else {
+                let assertion = match body {
+                    Ok((_, true)) => Err(format!("body is larger than {} bytes", MAX_BODY_BYTES)),
+                    Ok((body, false)) => check_body(check, &String::from_utf8_lossy(&body)),
+                    Err(e) => Err(format!("failed to read body: {}", e)),
+                };
+                match assertion {
+                    Ok(()) => (true, format!("HTTP {}", status_code)),
+                    Err(reason) => (
+                        false,
+                        format!("HTTP {}, assertion failed: {}", status_code, reason),
+                    ),
+                }
+            };
 
-            println!("{} OK ({})", service.name, status_code);
+            let result = if is_success {
+                println!("{} OK ({})", service.name, status_code);
+                CheckResult::up(Some(elapsed), message)
+            } else {
+                println!("{} FAILED: {}", service.name, message);
+                CheckResult::down(Some(elapsed), message)
+            };
+            let result = result.timings(Some(timings));
 
             state
                 .update_check_status(
                     service.name.clone(),
                     service.url.clone(),
                     "HTTP".to_string(),
-                    is_success,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    result,
+                    check,
                 )
                 .await;
         }
@@ -39,17 +117,166 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
             let message = format!("Error: {}", e);
             println!("{} FAILED: {}", service.name, e);
 
+            // Keep whatever phases completed, e.g. to show a slow DNS before a timeout.
+            let timings = http_timings(recorder.phases(), headers_at, None);
+
             state
                 .update_check_status(
                     service.name.clone(),
                     service.url.clone(),
                     "HTTP".to_string(),
-                    false,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::down(Some(elapsed), message).timings(Some(timings)),
+                    check,
                 )
                 .await;
         }
     }
 }
+
+/// Largest response body downloaded; reading stops there.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Reads the body up to `MAX_BODY_BYTES`, and whether it was cut off there.
+async fn read_body(resp: &mut reqwest::Response) -> reqwest::Result<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        let room = MAX_BODY_BYTES - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+fn build_request(
+    client: &Client,
+    service: &Service,
+    check: &CheckConfig,
+) -> Result<reqwest::RequestBuilder, String> {
+    let method = Method::from_bytes(check.method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid HTTP method: {}", check.method))?;
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &check.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| format!("Invalid value for header {}", name))?;
+        headers.insert(name, value);
+    }
+
+    let mut request = client.request(method, &service.url).headers(headers);
+    if let Some(body) = &check.body {
+        request = request.body(body.clone());
+    }
+
+    Ok(request)
+}
+
+/// Splits the time to response headers into DNS, connect (TCP + TLS) and
+/// time to first byte. Phases are `None` when a pooled connection was reused.
+/// A connection that could not be established takes all time after DNS, so
+/// the first byte time is then 0.
+fn http_timings(phases: Phases, headers_at: Duration, download: Option<Duration>) -> HttpTimings {
+    let dns = phases.dns.unwrap_or_default();
+    let connect = match phases.connect {
+        Some(connect) => Some(connect.saturating_sub(dns)),
+        None if phases.connect_attempted => Some(headers_at.saturating_sub(dns)),
+        None => None,
+    };
+    let ttfb = headers_at
+        .saturating_sub(dns)
+        .saturating_sub(connect.unwrap_or_default());
+
+    HttpTimings {
+        dns_ms: phases.dns.map(|d| d.as_millis() as u64),
+        connect_ms: connect.map(|c| c.as_millis() as u64),
+        ttfb_ms: ttfb.as_millis() as u64,
+        download_ms: download.map(|d| d.as_millis() as u64),
+        total_ms: (headers_at + download.unwrap_or_default()).as_millis() as u64,
+        connection_reused: !phases.connect_attempted,
+    }
+}
+
+fn check_body(check: &CheckConfig, body: &str) -> Result<(), String> {
+    if let Some(keyword) = &check.body_contains {
+        if !body.contains(keyword.as_str()) {
+            return Err(format!("body does not contain '{}'", keyword));
+        }
+    }
+
+    if let Some(keyword) = &check.body_not_contains {
+        if body.contains(keyword.as_str()) {
+            return Err(format!("body contains '{}'", keyword));
+        }
+    }
+
+    if let Some(regex) = &check.body_regex {
+        if !regex.is_match(body) {
+            return Err(format!("body does not match /{}/", regex.as_str()));
+        }
+    }
+
+    if let Some(json_path) = &check.json_path {
+        json_path.evaluate(body)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn fresh_connections_split_into_phases() {
+        let phases = Phases {
+            dns: Some(ms(5)),
+            connect: Some(ms(35)),
+            connect_attempted: true,
+        };
+        let timings = http_timings(phases, ms(100), Some(ms(20)));
+        assert_eq!(timings.dns_ms, Some(5));
+        assert_eq!(timings.connect_ms, Some(30));
+        assert_eq!(timings.ttfb_ms, 65);
+        assert_eq!(timings.download_ms, Some(20));
+        assert_eq!(timings.total_ms, 120);
+        assert!(!timings.connection_reused);
+    }
+
+    #[test]
+    fn reused_connections_only_have_first_byte_time() {
+        let phases = Phases {
+            dns: None,
+            connect: None,
+            connect_attempted: false,
+        };
+        let timings = http_timings(phases, ms(40), None);
+        assert_eq!((timings.dns_ms, timings.connect_ms), (None, None));
+        assert_eq!(timings.ttfb_ms, 40);
+        assert_eq!(timings.download_ms, None);
+        assert_eq!(timings.total_ms, 40);
+        assert!(timings.connection_reused);
+    }
+
+    #[test]
+    fn failed_connects_take_all_time_after_dns() {
+        let phases = Phases {
+            dns: Some(ms(5)),
+            connect: None,
+            connect_attempted: true,
+        };
+        let timings = http_timings(phases, ms(1000), None);
+        assert_eq!(timings.dns_ms, Some(5));
+        assert_eq!(timings.connect_ms, Some(995));
+        assert_eq!(timings.ttfb_ms, 0);
+        assert_eq!(timings.total_ms, 1000);
+        assert!(!timings.connection_reused);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::config::{HttpClientConfig, HttpVersion};
+use crate::models::service::CheckConfig;
+use crate::monitor::http_timing::{TimingLayer, TimingResolver};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, Identity, Proxy};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// HTTP clients shared by all `Http` checks of the monitoring runtime.
+///
+/// Clients are built lazily from the named profiles in `http_clients` and
+/// cached per profile and redirect policy, so checks reuse pooled connections.
+#[derive(Clone)]
+pub struct HttpClientPool {
+    profiles: Arc<HashMap<String, HttpClientConfig>>,
+    clients: Arc<Mutex<HashMap<ClientKey, Client>>>,
+}
+
+/// Profile name (`None` for the default profile), whether redirects are
+/// followed and whether HTTP/2 is spoken with prior knowledge.
+type ClientKey = (Option<String>, bool, bool);
+
+impl HttpClientPool {
+    pub fn new(profiles: HashMap<String, HttpClientConfig>) -> Self {
+        Self {
+            profiles: Arc::new(profiles),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the client for this check's `http_client` profile and redirect
+    /// policy, to request `url` with.
+    pub fn get(&self, check: &CheckConfig, url: &str) -> Result<Client, String> {
+        let default_profile = HttpClientConfig::default();
+        let profile = match &check.http_client {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .ok_or_else(|| format!("Unknown http_client: {}", name))?,
+            None => &default_profile,
+        };
+
+        // Over TLS, HTTP/2 is negotiated with ALPN instead.
+        let prior_knowledge = matches!(profile.http_version, HttpVersion::Http2)
+            && url
+                .get(..7)
+                .is_some_and(|s| s.eq_ignore_ascii_case("http://"));
+        let key = (
+            check.http_client.clone(),
+            check.follow_redirects,
+            prior_knowledge,
+        );
+
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let client =
+            build_client(profile, check.follow_redirects, prior_knowledge).map_err(|e| {
+                let name = check.http_client.as_deref().unwrap_or("default");
+                format!("Failed to build http_client '{}': {}", name, e)
+            })?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// HTTP version required by this check's `http_client` profile.
+    pub fn http_version(&self, check: &CheckConfig) -> HttpVersion {
+        check
+            .http_client
+            .as_ref()
+            .and_then(|name| self.profiles.get(name))
+            .map(|profile| profile.http_version)
+            .unwrap_or_default()
+    }
+}
+
+fn build_client(
+    profile: &HttpClientConfig,
+    follow_redirects: bool,
+    prior_knowledge: bool,
+) -> anyhow::Result<Client> {
+    let redirect = if follow_redirects {
+        Policy::default()
+    } else {
+        Policy::none()
+    };
+    let mut builder = Client::builder()
+        .redirect(redirect)
+        .dns_resolver(Arc::new(TimingResolver))
+        .connector_layer(TimingLayer);
+
+    if let Some(proxy) = &profile.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    if let Some(path) = &profile.ca_bundle {
+        for cert in Certificate::from_pem_bundle(&fs::read(path)?)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if let (Some(cert), Some(key)) = (&profile.client_cert, &profile.client_key) {
+        let identity = Identity::from_pkcs8_pem(&fs::read(cert)?, &fs::read(key)?)?;
+        builder = builder.identity(identity);
+    }
+
+    if profile.insecure_skip_verify {
+        builder = builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+
+    if let Some(user_agent) = &profile.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+
+    // `Http2` over TLS offers h2 through ALPN like `Auto`; the check fails if
+    // the server picks HTTP/1.1.
+    builder = match profile.http_version {
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Http2 if prior_knowledge => builder.http2_prior_knowledge(),
+        HttpVersion::Auto | HttpVersion::Http2 => builder,
+    };
+
+    Ok(builder.build()?)
+}
@@ -0,0 +1,134 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+tokio::task_local! {
+    static PHASES: PhaseRecorder;
+}
+
+/// Connection phase durations recorded while a request is in flight.
+///
+/// The shared `reqwest` clients run DNS resolution and connection setup inside
+/// the future of the request that needs them, so the hooks below find the
+/// recorder of the current check through a task-local. When a pooled
+/// connection is reused nothing is recorded; `connect` is only set once a
+/// connection was established, while `connect_attempted` also covers
+/// attempts that failed or timed out.
+#[derive(Clone, Default)]
+pub struct PhaseRecorder {
+    inner: Arc<Mutex<Phases>>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Phases {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub connect_attempted: bool,
+}
+
+impl PhaseRecorder {
+    /// Runs `future` with this recorder attached to the current task.
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        PHASES.scope(self.clone(), future).await
+    }
+
+    pub fn phases(&self) -> Phases {
+        *self.inner.lock().unwrap()
+    }
+
+    fn current() -> Option<Self> {
+        PHASES.try_with(|recorder| recorder.clone()).ok()
+    }
+
+    // Redirects may open several connections; their phases add up.
+    fn add_dns(&self, elapsed: Duration) {
+        let mut phases = self.inner.lock().unwrap();
+        phases.dns = Some(phases.dns.unwrap_or_default() + elapsed);
+    }
+
+    fn start_connect(&self) {
+        self.inner.lock().unwrap().connect_attempted = true;
+    }
+
+    fn add_connect(&self, elapsed: Duration) {
+        let mut phases = self.inner.lock().unwrap();
+        phases.connect = Some(phases.connect.unwrap_or_default() + elapsed);
+    }
+}
+
+/// System resolver (`getaddrinfo`) that records lookup time.
+pub struct TimingResolver;
+
+impl Resolve for TimingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let recorder = PhaseRecorder::current();
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs = tokio::net::lookup_host((host.as_str(), 0)).await;
+            if let Some(recorder) = recorder {
+                recorder.add_dns(start.elapsed());
+            }
+
+            let addrs: Vec<_> = addrs?.collect();
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Connector layer recording the time to an established connection.
+///
+/// This covers DNS, TCP connect and the TLS handshake, which `reqwest` runs as
+/// one step; the DNS share is subtracted when the timings are reported.
+#[derive(Clone)]
+pub struct TimingLayer;
+
+impl<S> Layer<S> for TimingLayer {
+    type Service = TimingConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimingConnector { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct TimingConnector<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for TimingConnector<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let recorder = PhaseRecorder::current();
+        let connecting = self.inner.call(request);
+
+        Box::pin(async move {
+            if let Some(recorder) = &recorder {
+                recorder.start_connect();
+            }
+            let start = Instant::now();
+            let result = connecting.await;
+            if let (Some(recorder), Ok(_)) = (recorder, &result) {
+                recorder.add_connect(start.elapsed());
+            }
+            result
+        })
+    }
+}
@@ -1,33 +1,296 @@
 use crate::config::Config;
 use crate::models::service::{CheckConfig, CheckType, Service};
+use crate::monitor::http_client::HttpClientPool;
+use crate::monitor::resolver::ResolverPool;
 use crate::state::AppState;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 pub mod dns_check;
 pub mod http_check;
+pub mod http_client;
+pub mod http_timing;
+pub mod reload;
+pub mod resolver;
 pub mod ssl_check;
 pub mod tcp_check;
 
-pub async fn start_monitoring(config: Config, state: AppState) {
-    for service in config.services {
-        for check in service.checks.clone() {
-            let state_clone = state.clone();
-            let service_clone = service.clone();
-            tokio::spawn(async move {
-                loop {
-                    run_check(&service_clone, &check, &state_clone).await;
-                    tokio::time::sleep(std::time::Duration::from_secs(check.interval_seconds))
-                        .await;
+/// Runs the check loops of the configured services and swaps them when the
+/// config changes.
+///
+/// Checks are grouped by service name and check type, the key state and
+/// history use. A group whose settings did not change keeps running, so its
+/// schedule is not disturbed by a reload.
+pub struct Monitor {
+    config: Config,
+    state: AppState,
+    resolvers: ResolverPool,
+    http_clients: HttpClientPool,
+    tasks: HashMap<CheckKey, CheckTasks>,
+}
+
+/// The loops of one service's checks of one type.
+struct CheckTasks {
+    /// Everything the loops depend on, to tell whether they need a restart.
+    fingerprint: Value,
+    /// Signalled to stop the loops once their current run is done.
+    stop: watch::Sender<()>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+/// How long a reload waits for stopped checks to finish their current run.
+const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Service name and check type.
+type CheckKey = (String, String);
+
+/// A configured check with the settings its loop depends on.
+type PlannedCheck = (Service, CheckConfig, Value);
+
+/// Check groups affected by a reload, for the log.
+#[derive(Default)]
+pub struct ReloadSummary {
+    pub started: usize,
+    pub restarted: usize,
+    pub stopped: usize,
+}
+
+impl Monitor {
+    /// Starts a loop for every configured check.
+    pub fn start(config: Config, state: AppState) -> Self {
+        let mut monitor = Self {
+            resolvers: ResolverPool::new(),
+            http_clients: HttpClientPool::new(config.http_clients.clone()),
+            tasks: HashMap::new(),
+            state,
+            config,
+        };
+        for (key, checks) in check_groups(&monitor.config) {
+            let tasks = monitor.spawn(&checks);
+            monitor.tasks.insert(key, tasks);
+        }
+        monitor
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Switches to `config`: stops the loops of removed and changed checks,
+    /// updates the state and starts loops for new and changed checks.
+    ///
+    /// Fails without changing anything if the notifiers cannot be set up.
+    /// Settings other than services, checks, HTTP client profiles, alerting
+    /// and maintenance windows only apply after a restart.
+    pub async fn apply(&mut self, config: Config) -> anyhow::Result<ReloadSummary> {
+        self.state.reload_settings(&config)?;
+
+        let mut groups = check_groups(&config);
+        let mut summary = ReloadSummary::default();
+        let mut stopping = Vec::new();
+        self.tasks.retain(|key, tasks| {
+            let keep = groups
+                .get(key)
+                .is_some_and(|checks| fingerprint(checks) == tasks.fingerprint);
+            if keep {
+                groups.remove(key);
+            } else {
+                let _ = tasks.stop.send(());
+                stopping.push(std::mem::take(&mut tasks.handles));
+                if groups.contains_key(key) {
+                    summary.restarted += 1;
+                } else {
+                    summary.stopped += 1;
                 }
+            }
+            keep
+        });
+        // Wait for running checks so none reports after its state is dropped
+        // or its replacement starts; abort those still running at the deadline.
+        let deadline = tokio::time::Instant::now() + STOP_TIMEOUT;
+        for mut handle in stopping.into_iter().flatten() {
+            if tokio::time::timeout_at(deadline, &mut handle)
+                .await
+                .is_err()
+            {
+                handle.abort();
+                let _ = handle.await;
+            }
+        }
+        self.state.reload_services(&config).await;
+
+        if json!(config.http_clients) != json!(self.config.http_clients) {
+            self.http_clients = HttpClientPool::new(config.http_clients.clone());
+        }
+        summary.started = groups.len() - summary.restarted;
+        for (key, checks) in groups {
+            let tasks = self.spawn(&checks);
+            self.tasks.insert(key, tasks);
+        }
+
+        self.config = config;
+        Ok(summary)
+    }
+
+    fn spawn(&self, checks: &[PlannedCheck]) -> CheckTasks {
+        let (stop, _) = watch::channel(());
+        let handles = checks
+            .iter()
+            .map(|(service, check, _)| {
+                let service = service.clone();
+                let check = check.clone();
+                let state = self.state.clone();
+                let resolvers = self.resolvers.clone();
+                let http_clients = self.http_clients.clone();
+                let mut stopped = stop.subscribe();
+                let interval = std::time::Duration::from_secs(check.interval_seconds);
+                tokio::spawn(async move {
+                    loop {
+                        run_check(&service, &check, &state, &resolvers, &http_clients).await;
+                        tokio::select! {
+                            _ = tokio::time::sleep(interval) => {}
+                            _ = stopped.changed() => break,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        CheckTasks {
+            fingerprint: fingerprint(checks),
+            stop,
+            handles,
+        }
+    }
+}
+
+/// Checks of `config` by service name and check type, each with the
+/// settings its loop depends on.
+fn check_groups(config: &Config) -> HashMap<CheckKey, Vec<PlannedCheck>> {
+    let mut groups: HashMap<_, Vec<_>> = HashMap::new();
+    for service in &config.services {
+        for check in &service.checks {
+            let http_client = check
+                .http_client
+                .as_ref()
+                .and_then(|name| config.http_clients.get(name));
+            let settings = json!({
+                "url": service.url,
+                "check": check,
+                "http_client": http_client,
             });
+            groups
+                .entry((service.name.clone(), check.check_type.label().to_string()))
+                .or_default()
+                .push((service.clone(), check.clone(), settings));
         }
     }
+    groups
 }
 
-async fn run_check(service: &Service, check: &CheckConfig, state: &AppState) {
+fn fingerprint(checks: &[PlannedCheck]) -> Value {
+    checks
+        .iter()
+        .map(|(_, _, settings)| settings.clone())
+        .collect()
+}
+
+async fn run_check(
+    service: &Service,
+    check: &CheckConfig,
+    state: &AppState,
+    resolvers: &ResolverPool,
+    http_clients: &HttpClientPool,
+) {
     match &check.check_type {
-        CheckType::Http => http_check::run(service, check, state).await,
+        CheckType::Http => http_check::run(service, check, state, http_clients).await,
         CheckType::Tcp => tcp_check::run(service, check, state).await,
-        CheckType::Dns => dns_check::run(service, check, state).await,
+        CheckType::Dns => dns_check::run(service, check, state, resolvers).await,
         CheckType::Ssl => ssl_check::run(service, check, state).await,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(api_timeout_ms: u64) -> Config {
+        serde_json::from_value(json!({
+            "services": [
+                {
+                    "name": "db",
+                    "url": "127.0.0.1:1",
+                    "checks": [{ "check_type": "Tcp", "interval_seconds": 3600, "timeout_ms": 500 }]
+                },
+                {
+                    "name": "api",
+                    "url": "127.0.0.1:2",
+                    "checks": [
+                        {
+                            "check_type": "Tcp",
+                            "interval_seconds": 3600,
+                            "timeout_ms": api_timeout_ms
+                        },
+                        { "check_type": "Dns", "interval_seconds": 3600, "timeout_ms": 500 }
+                    ]
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn key(service: &str, check_type: &str) -> CheckKey {
+        (service.to_string(), check_type.to_string())
+    }
+
+    #[test]
+    fn only_the_changed_check_group_gets_a_new_fingerprint() {
+        let old = check_groups(&config(500));
+        let new = check_groups(&config(800));
+        assert_eq!(old.len(), 3);
+
+        let changed: Vec<&CheckKey> = old
+            .keys()
+            .filter(|key| fingerprint(&old[*key]) != fingerprint(&new[*key]))
+            .collect();
+        assert_eq!(changed, [&key("api", "TCP")]);
+    }
+
+    #[tokio::test]
+    async fn reload_restarts_only_changed_checks() {
+        let state = AppState::for_test("monitor-reload", &config(500));
+        let mut monitor = Monitor::start(config(500), state);
+        let task_ids = |monitor: &Monitor, key: &CheckKey| -> Vec<tokio::task::Id> {
+            monitor.tasks[key].handles.iter().map(|h| h.id()).collect()
+        };
+        let db = task_ids(&monitor, &key("db", "TCP"));
+        let api_dns = task_ids(&monitor, &key("api", "DNS"));
+        let api_tcp = task_ids(&monitor, &key("api", "TCP"));
+
+        let summary = monitor.apply(config(800)).await.unwrap();
+        assert_eq!(
+            (summary.started, summary.restarted, summary.stopped),
+            (0, 1, 0)
+        );
+        assert_eq!(task_ids(&monitor, &key("db", "TCP")), db);
+        assert_eq!(task_ids(&monitor, &key("api", "DNS")), api_dns);
+        assert_ne!(task_ids(&monitor, &key("api", "TCP")), api_tcp);
+        assert!(monitor.tasks[&key("db", "TCP")]
+            .handles
+            .iter()
+            .all(|h| !h.is_finished()));
+
+        // Removing a service stops its checks.
+        let mut without_db = config(800);
+        without_db.services.retain(|s| s.name != "db");
+        let summary = monitor.apply(without_db).await.unwrap();
+        assert_eq!(
+            (summary.started, summary.restarted, summary.stopped),
+            (0, 0, 1)
+        );
+        assert!(!monitor.tasks.contains_key(&key("db", "TCP")));
+        assert_eq!(task_ids(&monitor, &key("api", "DNS")), api_dns);
+    }
+}
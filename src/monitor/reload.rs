@@ -0,0 +1,130 @@
+use crate::config::Config;
+use crate::monitor::Monitor;
+use serde_json::json;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+
+/// How often the config file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Reloads the config at `path` whenever the file changes or the process
+/// receives SIGHUP.
+///
+/// An invalid config is reported and the running one is kept, so a
+/// half-saved file is simply picked up again once it is complete.
+pub async fn watch_config(path: &'static str, mut monitor: Monitor) {
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last = file_version(path);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let version = file_version(path);
+                if version == last {
+                    continue;
+                }
+                last = version;
+                println!("{} changed, reloading", path);
+            }
+            _ = hangup.recv() => {
+                last = file_version(path);
+                println!("Received SIGHUP, reloading {}", path);
+            }
+        }
+        reload(path, &mut monitor).await;
+    }
+}
+
+async fn reload(path: &str, monitor: &mut Monitor) {
+    let config = match Config::load(path) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Config reload failed, keeping the running config: {}", e);
+            return;
+        }
+    };
+
+    let needs_restart = restart_only_changes(monitor.config(), &config);
+    match monitor.apply(config).await {
+        Ok(summary) => println!(
+            "Config reloaded: {} checks started, {} restarted, {} stopped",
+            summary.started, summary.restarted, summary.stopped
+        ),
+        Err(e) => {
+            println!("Config reload failed, keeping the running config: {}", e);
+            return;
+        }
+    }
+    if !needs_restart.is_empty() {
+        println!(
+            "Changes to {} only apply after a restart",
+            needs_restart.join(", ")
+        );
+    }
+}
+
+/// Modification time and size, which change whenever the file is saved.
+fn file_version(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Settings that changed but are only read at startup.
+fn restart_only_changes(old: &Config, new: &Config) -> Vec<&'static str> {
+    let settings = [
+        (
+            "history_path",
+            json!(old.history_path),
+            json!(new.history_path),
+        ),
+        (
+            "history_retention_days",
+            json!(old.history_retention_days),
+            json!(new.history_retention_days),
+        ),
+        (
+            "history_buffer_size",
+            json!(old.history_buffer_size),
+            json!(new.history_buffer_size),
+        ),
+        (
+            "uptime_windows",
+            json!(old.uptime_windows),
+            json!(new.uptime_windows),
+        ),
+        (
+            "latency_windows",
+            json!(old.latency_windows),
+            json!(new.latency_windows),
+        ),
+    ];
+    settings
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(name, _, _)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_settings_that_need_a_restart() {
+        let old: Config = serde_json::from_value(json!({ "services": [] })).unwrap();
+        let new: Config = serde_json::from_value(json!({
+            "services": [],
+            "history_retention_days": 30,
+            "latency_windows": ["1h"],
+            "notifiers": {}
+        }))
+        .unwrap();
+
+        assert!(restart_only_changes(&old, &old.clone()).is_empty());
+        assert_eq!(
+            restart_only_changes(&old, &new),
+            ["history_retention_days", "latency_windows"]
+        );
+    }
+}
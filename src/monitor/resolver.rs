@@ -0,0 +1,121 @@
+use crate::models::service::CheckConfig;
+use crate::utils::net::normalize_host_port;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::system_conf::read_system_conf;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// DNS resolvers shared by all `Dns` checks of the monitoring runtime.
+///
+/// Checks with the same nameservers and timeout/attempt settings share one
+/// resolver and its connections. Answers are never cached, so every check
+/// queries the nameservers and sees outages and record changes. The system
+/// configuration is read once; if it cannot be loaded, checks that rely on it
+/// fail with that error instead of panicking.
+#[derive(Clone)]
+pub struct ResolverPool {
+    system: Arc<Result<(ResolverConfig, ResolverOpts), String>>,
+    resolvers: Arc<Mutex<HashMap<ResolverKey, TokioAsyncResolver>>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ResolverKey {
+    nameservers: Vec<String>,
+    timeout_ms: u64,
+    attempts: usize,
+}
+
+impl ResolverPool {
+    pub fn new() -> Self {
+        let system = read_system_conf()
+            .map_err(|e| format!("Failed to load system resolver configuration: {}", e));
+        if let Err(e) = &system {
+            println!("{}", e);
+        }
+
+        Self {
+            system: Arc::new(system),
+            resolvers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the resolver for this check's nameservers and timeout/attempt settings.
+    pub fn get(&self, check: &CheckConfig) -> Result<TokioAsyncResolver, String> {
+        let key = ResolverKey {
+            nameservers: check.nameservers.clone(),
+            timeout_ms: check.timeout_ms,
+            attempts: check.attempts.max(1),
+        };
+
+        let mut resolvers = self.resolvers.lock().unwrap();
+        if let Some(resolver) = resolvers.get(&key) {
+            return Ok(resolver.clone());
+        }
+
+        let (config, mut opts) = if key.nameservers.is_empty() {
+            self.system.as_ref().clone()?
+        } else {
+            (custom_config(&key.nameservers)?, ResolverOpts::default())
+        };
+
+        // Split the check timeout across attempts; the caller still enforces
+        // `timeout_ms` as a hard limit for the whole lookup.
+        opts.attempts = key.attempts;
+        opts.timeout = Duration::from_millis((key.timeout_ms / key.attempts as u64).max(1));
+        opts.cache_size = 0;
+
+        let resolver = TokioAsyncResolver::tokio(config, opts);
+        resolvers.insert(key, resolver.clone());
+        Ok(resolver)
+    }
+}
+
+fn custom_config(nameservers: &[String]) -> Result<ResolverConfig, String> {
+    let mut config = ResolverConfig::new();
+    for raw in nameservers {
+        let addr = nameserver_addr(raw)?;
+        config.add_name_server(NameServerConfig::new(addr, Protocol::Udp));
+        config.add_name_server(NameServerConfig::new(addr, Protocol::Tcp));
+    }
+    Ok(config)
+}
+
+/// Parses `ip`, `ip:port` or `[ipv6]:port`; a bare IPv6 address such as
+/// `2001:db8::53` uses port 53.
+fn nameserver_addr(raw: &str) -> Result<SocketAddr, String> {
+    let raw = raw.trim();
+    if let Ok(ip) = raw.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, 53));
+    }
+    if let Ok(addr) = raw.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+
+    let (host, port) = normalize_host_port(raw, 53);
+    let ip: IpAddr = host
+        .parse()
+        .map_err(|_| format!("Invalid nameserver address: {}", raw))?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nameserver_addresses() {
+        let parse = |raw| nameserver_addr(raw).map(|a| a.to_string());
+        assert_eq!(parse("1.1.1.1"), Ok("1.1.1.1:53".to_string()));
+        assert_eq!(parse("127.0.0.1:5353"), Ok("127.0.0.1:5353".to_string()));
+        assert_eq!(parse("2001:db8::53"), Ok("[2001:db8::53]:53".to_string()));
+        assert_eq!(
+            parse("[2001:db8::53]:5353"),
+            Ok("[2001:db8::53]:5353".to_string())
+        );
+        assert_eq!(parse("[::1]"), Ok("[::1]:53".to_string()));
+        assert!(parse("dns.example.com").is_err());
+    }
+}
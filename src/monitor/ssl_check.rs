@@ -1,11 +1,15 @@
 use crate::models::service::{CheckConfig, Service};
-use crate::state::AppState;
-use crate::utils::net::normalize_host_port;
+use crate::state::{AppState, CertificateInfo, CheckResult, HealthStatus};
+use crate::utils::net::{hostname_matches, normalize_host_port};
+use chrono::{DateTime, Utc};
 use native_tls::TlsConnector as NativeTlsConnector;
-use std::time::Instant;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 use tokio_native_tls::TlsConnector;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
 
 pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
     let start = Instant::now();
@@ -13,12 +17,8 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
     let (host, port) = normalize_host_port(&service.url, 443);
     let addr = format!("{}:{}", host, port);
 
-    let connect_future = TcpStream::connect(addr);
-    let stream_result = timeout(
-        std::time::Duration::from_millis(check.timeout_ms),
-        connect_future,
-    )
-    .await;
+    let connect_future = TcpStream::connect(addr.clone());
+    let stream_result = timeout(Duration::from_millis(check.timeout_ms), connect_future).await;
     let elapsed = start.elapsed().as_millis() as u64;
 
     match stream_result {
@@ -27,25 +27,64 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
             match NativeTlsConnector::new() {
                 Ok(native) => {
                     let connector = TlsConnector::from(native);
-                    match connector.connect(&host, stream).await {
-                        Ok(_tls_stream) => {
-                            let message = format!("TLS handshake succeeded for {}", host);
-                            println!("{} SSL OK", service.name);
+                    let handshake = timeout(
+                        Duration::from_millis(check.timeout_ms),
+                        connector.connect(&host, stream),
+                    )
+                    .await
+                    .map_err(|_| format!("timed out after {}ms", check.timeout_ms))
+                    .and_then(|result| result.map_err(|e| e.to_string()));
+                    match handshake {
+                        Ok(tls_stream) => {
+                            let certificate = tls_stream
+                                .get_ref()
+                                .peer_certificate()
+                                .ok()
+                                .flatten()
+                                .and_then(|cert| cert.to_der().ok())
+                                .and_then(|der| certificate_info(&der, &host));
+
+                            let result = evaluate_certificate(&host, certificate, check, elapsed);
+                            match result.status {
+                                HealthStatus::Up => println!("{} SSL OK", service.name),
+                                _ => println!("{} SSL {}", service.name, result.message),
+                            }
 
                             state
                                 .update_check_status(
                                     service.name.clone(),
                                     service.url.clone(),
                                     "SSL".to_string(),
-                                    true,
-                                    Some(elapsed),
-                                    message,
-                                    check.interval_seconds,
+                                    result,
+                                    check,
                                 )
                                 .await;
                         }
                         Err(e) => {
-                            let message = format!("TLS handshake failed: {}", e);
+                            // Re-connect without verification so the failure can be explained
+                            // (expired, wrong host, ...) from the certificate itself.
+                            let certificate = inspect_unverified(&addr, &host, check).await;
+                            let mut message = format!("TLS handshake failed: {}", e);
+                            if let Some(cert) = &certificate {
+                                if cert.days_until_expiry < 0 {
+                                    message.push_str(&format!(
+                                        "; certificate expired {} days ago",
+                                        -cert.days_until_expiry
+                                    ));
+                                }
+                                if cert.not_before > Utc::now() {
+                                    message.push_str(&format!(
+                                        "; certificate is not valid until {}",
+                                        cert.not_before.format("%Y-%m-%d %H:%M UTC")
+                                    ));
+                                }
+                                if !cert.hostname_match {
+                                    message.push_str(&format!(
+                                        "; certificate is not valid for {}",
+                                        host
+                                    ));
+                                }
+                            }
                             println!("{} SSL FAILED: {}", service.name, e);
 
                             state
@@ -53,10 +92,9 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                                     service.name.clone(),
                                     service.url.clone(),
                                     "SSL".to_string(),
-                                    false,
-                                    Some(elapsed),
-                                    message,
-                                    check.interval_seconds,
+                                    CheckResult::down(Some(elapsed), message)
+                                        .certificate(certificate),
+                                    check,
                                 )
                                 .await;
                         }
@@ -71,10 +109,8 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                             service.name.clone(),
                             service.url.clone(),
                             "SSL".to_string(),
-                            false,
-                            Some(elapsed),
-                            message,
-                            check.interval_seconds,
+                            CheckResult::down(Some(elapsed), message),
+                            check,
                         )
                         .await;
                 }
@@ -89,10 +125,8 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                     service.name.clone(),
                     service.url.clone(),
                     "SSL".to_string(),
-                    false,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::down(Some(elapsed), message),
+                    check,
                 )
                 .await;
         }
@@ -105,12 +139,256 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                     service.name.clone(),
                     service.url.clone(),
                     "SSL".to_string(),
-                    false,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::down(Some(elapsed), message),
+                    check,
                 )
                 .await;
         }
     }
 }
+
+fn evaluate_certificate(
+    host: &str,
+    certificate: Option<CertificateInfo>,
+    check: &CheckConfig,
+    elapsed: u64,
+) -> CheckResult {
+    let Some(cert) = certificate else {
+        let message = format!(
+            "TLS handshake succeeded for {}, but the certificate could not be read",
+            host
+        );
+        return CheckResult::up(Some(elapsed), message);
+    };
+
+    let days = cert.days_until_expiry;
+    let (status, message) = if days < 0 {
+        (
+            HealthStatus::Down,
+            format!("Certificate for {} expired {} days ago", host, -days),
+        )
+    } else if cert.not_before > Utc::now() {
+        (
+            HealthStatus::Down,
+            format!(
+                "Certificate for {} is not valid until {}",
+                host,
+                cert.not_before.format("%Y-%m-%d %H:%M UTC")
+            ),
+        )
+    } else if !cert.hostname_match {
+        (
+            HealthStatus::Down,
+            format!("Certificate is not valid for {}", host),
+        )
+    } else if days <= check.expiry_warning_days {
+        (
+            HealthStatus::ExpiringSoon,
+            format!(
+                "Certificate for {} expires in {} days ({})",
+                host,
+                days,
+                cert.not_after.format("%Y-%m-%d")
+            ),
+        )
+    } else {
+        (
+            HealthStatus::Up,
+            format!(
+                "TLS handshake succeeded for {}, certificate expires in {} days",
+                host, days
+            ),
+        )
+    };
+
+    CheckResult::with_status(status, Some(elapsed), message).certificate(Some(cert))
+}
+
+async fn inspect_unverified(
+    addr: &str,
+    host: &str,
+    check: &CheckConfig,
+) -> Option<CertificateInfo> {
+    let native = NativeTlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .build()
+        .ok()?;
+    let connector = TlsConnector::from(native);
+
+    let inspect = async {
+        let stream = TcpStream::connect(addr).await.ok()?;
+        let tls_stream = connector.connect(host, stream).await.ok()?;
+        let der = tls_stream
+            .get_ref()
+            .peer_certificate()
+            .ok()??
+            .to_der()
+            .ok()?;
+        certificate_info(&der, host)
+    };
+
+    timeout(Duration::from_millis(check.timeout_ms), inspect)
+        .await
+        .ok()
+        .flatten()
+}
+
+fn certificate_info(der: &[u8], host: &str) -> Option<CertificateInfo> {
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+    let mut subject_alt_names = Vec::new();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            match name {
+                GeneralName::DNSName(dns) => subject_alt_names.push(dns.to_string()),
+                GeneralName::IPAddress(bytes) => {
+                    let ip = match bytes.len() {
+                        4 => <[u8; 4]>::try_from(*bytes).ok().map(IpAddr::from),
+                        16 => <[u8; 16]>::try_from(*bytes).ok().map(IpAddr::from),
+                        _ => None,
+                    };
+                    if let Some(ip) = ip {
+                        subject_alt_names.push(ip.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let common_names = cert
+        .subject()
+        .iter_common_name()
+        .filter_map(|cn| cn.as_str().ok());
+    let hostname_match = names_match(&subject_alt_names, common_names, host);
+
+    let validity = cert.validity();
+    let not_before = DateTime::<Utc>::from_timestamp(validity.not_before.timestamp(), 0)?;
+    let not_after = DateTime::<Utc>::from_timestamp(validity.not_after.timestamp(), 0)?;
+    let days_until_expiry = (not_after - Utc::now()).num_seconds().div_euclid(86_400);
+
+    Some(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        subject_alt_names,
+        not_before,
+        not_after,
+        days_until_expiry,
+        hostname_match,
+    })
+}
+
+/// Whether a certificate with these names is valid for `host`. The subject CN
+/// only counts when there are no SANs (RFC 6125).
+fn names_match<'a>(
+    subject_alt_names: &[String],
+    common_names: impl IntoIterator<Item = &'a str>,
+    host: &str,
+) -> bool {
+    if subject_alt_names.is_empty() {
+        common_names
+            .into_iter()
+            .any(|cn| hostname_matches(cn, host))
+    } else {
+        subject_alt_names
+            .iter()
+            .any(|name| hostname_matches(name, host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn check() -> CheckConfig {
+        serde_json::from_value(serde_json::json!({
+            "check_type": "Ssl",
+            "interval_seconds": 3600,
+            "timeout_ms": 1000,
+            "expiry_warning_days": 14
+        }))
+        .unwrap()
+    }
+
+    /// A certificate for `example.com` valid from `issued` until `expires`
+    /// days from now.
+    fn certificate(issued: i64, expires: i64) -> CertificateInfo {
+        let now = Utc::now();
+        let not_after = now + Duration::days(expires) + Duration::hours(1);
+        CertificateInfo {
+            subject: "CN=example.com".to_string(),
+            issuer: "CN=Test CA".to_string(),
+            subject_alt_names: vec!["example.com".to_string()],
+            not_before: now + Duration::days(issued),
+            not_after,
+            days_until_expiry: (not_after - now).num_seconds().div_euclid(86_400),
+            hostname_match: true,
+        }
+    }
+
+    fn evaluate(cert: CertificateInfo) -> CheckResult {
+        evaluate_certificate("example.com", Some(cert), &check(), 20)
+    }
+
+    #[test]
+    fn valid_certificates_are_up() {
+        let result = evaluate(certificate(-30, 60));
+        assert_eq!(result.status, HealthStatus::Up);
+        assert_eq!(
+            result.message,
+            "TLS handshake succeeded for example.com, certificate expires in 60 days"
+        );
+    }
+
+    #[test]
+    fn expired_certificates_are_down() {
+        let result = evaluate(certificate(-90, -3));
+        assert_eq!(result.status, HealthStatus::Down);
+        assert_eq!(
+            result.message,
+            "Certificate for example.com expired 3 days ago"
+        );
+    }
+
+    #[test]
+    fn certificates_not_yet_valid_are_down() {
+        let result = evaluate(certificate(2, 90));
+        assert_eq!(result.status, HealthStatus::Down);
+        assert!(
+            result
+                .message
+                .starts_with("Certificate for example.com is not valid until "),
+            "{}",
+            result.message
+        );
+    }
+
+    #[test]
+    fn certificates_within_the_warning_threshold_are_expiring_soon() {
+        for days in [0, 14] {
+            let result = evaluate(certificate(-90, days));
+            assert_eq!(result.status, HealthStatus::ExpiringSoon, "{}", days);
+        }
+        assert_eq!(evaluate(certificate(-90, 15)).status, HealthStatus::Up);
+    }
+
+    #[test]
+    fn certificates_for_another_host_are_down() {
+        let mut cert = certificate(-30, 60);
+        cert.hostname_match = false;
+        let result = evaluate(cert);
+        assert_eq!(result.status, HealthStatus::Down);
+        assert_eq!(result.message, "Certificate is not valid for example.com");
+    }
+
+    #[test]
+    fn common_name_only_counts_without_sans() {
+        let sans = ["www.example.com".to_string()];
+        assert!(names_match(&[], ["example.com"], "example.com"));
+        assert!(!names_match(&sans, ["example.com"], "example.com"));
+        assert!(names_match(&sans, ["example.com"], "WWW.example.com."));
+        assert!(!names_match(&[], [], "example.com"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::models::service::{CheckConfig, Service};
-use crate::state::AppState;
+use crate::state::{AppState, CheckResult};
 use crate::utils::net::normalize_host_port;
 use std::time::Instant;
 use tokio::net::TcpStream;
@@ -29,10 +29,8 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                     service.name.clone(),
                     service.url.clone(),
                     "TCP".to_string(),
-                    true,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::up(Some(elapsed), message),
+                    check,
                 )
                 .await;
         }
@@ -45,10 +43,8 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                     service.name.clone(),
                     service.url.clone(),
                     "TCP".to_string(),
-                    false,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::down(Some(elapsed), message),
+                    check,
                 )
                 .await;
         }
@@ -61,10 +57,8 @@ pub async fn run(service: &Service, check: &CheckConfig, state: &AppState) {
                     service.name.clone(),
                     service.url.clone(),
                     "TCP".to_string(),
-                    false,
-                    Some(elapsed),
-                    message,
-                    check.interval_seconds,
+                    CheckResult::down(Some(elapsed), message),
+                    check,
                 )
                 .await;
         }
@@ -1,7 +1,19 @@
-use chrono::{DateTime, Utc};
+use crate::alert::dispatcher::{Delivery, Dispatcher};
+use crate::alert::{AlertEvent, AlertKind};
+use crate::config::{Config, EscalationPolicy};
+use crate::maintenance::{Maintenance, MaintenanceSchedule};
+use crate::models::incident::Incident;
+use crate::models::maintenance::MaintenanceWindow;
+use crate::models::service::CheckConfig;
+use crate::models::window::TimeWindow;
+use crate::stats::flapping::FlapDetector;
+use crate::stats::latency::{LatencySummary, LatencyTracker};
+use crate::stats::uptime::{UptimeSample, UptimeTracker};
+use crate::storage::Store;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock as SyncRwLock};
 use tokio::sync::RwLock;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -11,10 +23,26 @@ pub struct CheckStatus {
     pub last_check: DateTime<Utc>,
     pub response_time_ms: Option<u64>,
     pub uptime_percentage: f64,
+    pub uptime_windows: Vec<WindowUptime>,
+    pub latency: Vec<WindowLatency>,
     pub total_checks: u64,
     pub successful_checks: u64,
     pub message: String,
     pub interval_seconds: u64,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    /// Status the check is moving to while it waits for `failure_threshold` /
+    /// `recovery_threshold` consecutive results.
+    pub pending_status: Option<HealthStatus>,
+    /// Weighted share of recent results that changed state, in percent.
+    pub state_change_percentage: f64,
+    /// Status behind `Flapping`, restored before the next result is applied.
+    #[serde(skip)]
+    settled_status: Option<HealthStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<CertificateInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<HttpTimings>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -25,99 +53,681 @@ pub struct ServiceStatus {
     pub last_check: DateTime<Utc>,
     pub response_time_ms: Option<u64>,
     pub uptime_percentage: f64,
+    pub uptime_windows: Vec<WindowUptime>,
     pub total_checks: u64,
     pub successful_checks: u64,
     pub message: String,
     pub checks: Vec<CheckStatus>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// Time-weighted uptime over one rolling window; `None` until anything was
+/// observed in it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowUptime {
+    pub window: String,
+    pub uptime_percentage: Option<f64>,
+}
+
+/// Response time statistics over one rolling window; the summary fields are
+/// omitted while the window has no samples.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowLatency {
+    pub window: String,
+    pub samples: u64,
+    #[serde(flatten)]
+    pub summary: Option<LatencySummary>,
+}
+
+impl ServiceStatus {
+    fn new(name: String, url: String) -> Self {
+        Self {
+            name,
+            url,
+            status: HealthStatus::Unknown,
+            last_check: Utc::now(),
+            response_time_ms: None,
+            uptime_percentage: 0.0,
+            uptime_windows: Vec::new(),
+            total_checks: 0,
+            successful_checks: 0,
+            message: String::new(),
+            checks: Vec::new(),
+            depends_on: Vec::new(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum HealthStatus {
     Up,
     Down,
+    ExpiringSoon,
     Unknown,
+    /// Service-level only: a maintenance window is active.
+    Maintenance,
+    /// Service-level only: down while a service it depends on is down.
+    Impacted,
+    /// Changing state too often to be trusted; see `FlapDetector`.
+    Flapping,
+    /// Working but slower than `response_time_warning_ms`; for services,
+    /// also when a check is `ExpiringSoon`.
+    Degraded,
+}
+
+impl HealthStatus {
+    /// Whether the check is working, i.e. counts towards uptime.
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self,
+            HealthStatus::Up | HealthStatus::ExpiringSoon | HealthStatus::Degraded
+        )
+    }
+}
+
+/// Peer certificate details reported by `Ssl` checks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub subject_alt_names: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub days_until_expiry: i64,
+    pub hostname_match: bool,
+}
+
+/// Per-phase timings of an `Http` check, in milliseconds.
+///
+/// `connect_ms` covers both the TCP connect and the TLS handshake. DNS and
+/// connect are `None` when the request reused a pooled connection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpTimings {
+    pub dns_ms: Option<u64>,
+    pub connect_ms: Option<u64>,
+    pub ttfb_ms: u64,
+    pub download_ms: Option<u64>,
+    pub total_ms: u64,
+    pub connection_reused: bool,
+}
+
+/// A single check run, as kept in history and persisted by the store.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub timestamp: DateTime<Utc>,
+    pub status: HealthStatus,
+    pub success: bool,
+    pub response_time_ms: Option<u64>,
+    pub message: String,
+    /// Taken during maintenance, so not counted towards uptime.
+    #[serde(default)]
+    pub maintenance: bool,
+}
+
+/// Outcome of a single check run, as reported by the check implementations.
+pub struct CheckResult {
+    pub status: HealthStatus,
+    pub response_time_ms: Option<u64>,
+    pub message: String,
+    pub certificate: Option<CertificateInfo>,
+    pub timings: Option<HttpTimings>,
+}
+
+impl CheckResult {
+    pub fn up(response_time_ms: Option<u64>, message: String) -> Self {
+        Self::with_status(HealthStatus::Up, response_time_ms, message)
+    }
+
+    pub fn down(response_time_ms: Option<u64>, message: String) -> Self {
+        Self::with_status(HealthStatus::Down, response_time_ms, message)
+    }
+
+    pub fn with_status(
+        status: HealthStatus,
+        response_time_ms: Option<u64>,
+        message: String,
+    ) -> Self {
+        Self {
+            status,
+            response_time_ms,
+            message,
+            certificate: None,
+            timings: None,
+        }
+    }
+
+    pub fn certificate(mut self, certificate: Option<CertificateInfo>) -> Self {
+        self.certificate = certificate;
+        self
+    }
+
+    pub fn timings(mut self, timings: Option<HttpTimings>) -> Self {
+        self.timings = timings;
+        self
+    }
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub services: Arc<RwLock<HashMap<String, ServiceStatus>>>,
+    store: Option<Store>,
+    history: Arc<RwLock<HashMap<CheckKey, VecDeque<CheckRecord>>>>,
+    history_limit: usize,
+    uptime: Arc<RwLock<HashMap<CheckKey, UptimeTracker>>>,
+    uptime_windows: Arc<Vec<TimeWindow>>,
+    latency: Arc<RwLock<HashMap<CheckKey, LatencyTracker>>>,
+    latency_windows: Arc<Vec<TimeWindow>>,
+    flapping: Arc<RwLock<HashMap<CheckKey, FlapDetector>>>,
+    incidents: Arc<RwLock<IncidentLog>>,
+    incident_retention: Duration,
+    settings: Arc<SyncRwLock<Arc<ServiceSettings>>>,
+    maintenance: Arc<RwLock<MaintenanceSchedule>>,
+}
+
+/// Alerting and service settings taken from the config, replaced as a whole
+/// when it is reloaded.
+struct ServiceSettings {
+    dispatcher: Dispatcher,
+    /// Escalation policy of each service that has one.
+    escalations: HashMap<String, EscalationPolicy>,
+    service_tags: HashMap<String, Vec<String>>,
+    /// `depends_on` of each service.
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl ServiceSettings {
+    fn new(config: &Config, dispatcher: Dispatcher) -> Self {
+        Self {
+            dispatcher,
+            escalations: config
+                .services
+                .iter()
+                .filter_map(|service| {
+                    let policy = service.escalation_policy.as_ref()?;
+                    let policy = config.escalation_policies.get(policy)?;
+                    Some((service.name.clone(), policy.clone()))
+                })
+                .collect(),
+            service_tags: config
+                .services
+                .iter()
+                .map(|s| (s.name.clone(), s.tags.clone()))
+                .collect(),
+            dependencies: config
+                .services
+                .iter()
+                .map(|s| (s.name.clone(), s.depends_on.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A maintenance window as listed by the API.
+#[derive(Serialize)]
+pub struct MaintenanceStatus {
+    #[serde(flatten)]
+    pub window: MaintenanceWindow,
+    pub active: bool,
+}
+
+/// Why an incident could not be acknowledged.
+pub enum AckError {
+    NotFound,
+    Resolved,
 }
 
+/// Open incidents and those closed within the retention period, oldest first.
+#[derive(Default)]
+struct IncidentLog {
+    incidents: VecDeque<Incident>,
+    last_id: u64,
+}
+
+/// Service name and check type.
+type CheckKey = (String, String);
+
+/// Upper bound on results returned by one history query.
+const MAX_HISTORY_RESULTS: usize = 10_000;
+
 impl AppState {
-    pub fn new() -> Self {
+    /// State that persists every check result to `store`, keeps the last
+    /// `history_buffer_size` results of each check in memory and tracks
+    /// uptime and response times over the configured windows. Incidents are
+    /// reported through `dispatcher`.
+    pub fn with_store(store: Store, dispatcher: Dispatcher, config: &Config) -> Self {
         Self {
             services: Arc::new(RwLock::new(HashMap::new())),
+            store: Some(store),
+            history: Arc::new(RwLock::new(HashMap::new())),
+            history_limit: config.history_buffer_size,
+            uptime: Arc::new(RwLock::new(HashMap::new())),
+            uptime_windows: Arc::new(config.uptime_windows.clone()),
+            latency: Arc::new(RwLock::new(HashMap::new())),
+            latency_windows: Arc::new(config.latency_windows.clone()),
+            flapping: Arc::new(RwLock::new(HashMap::new())),
+            incidents: Arc::new(RwLock::new(IncidentLog::default())),
+            incident_retention: Duration::days(config.history_retention_days as i64),
+            settings: Arc::new(SyncRwLock::new(Arc::new(ServiceSettings::new(
+                config, dispatcher,
+            )))),
+            maintenance: Arc::new(RwLock::new(MaintenanceSchedule::new(config_maintenance(
+                config,
+            )))),
+        }
+    }
+
+    fn settings(&self) -> Arc<ServiceSettings> {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Switches to the notifiers, alert rules, escalation policies, tags and
+    /// dependencies of `config`.
+    ///
+    /// Fails, keeping the current settings, if a notifier cannot be set up.
+    pub fn reload_settings(&self, config: &Config) -> anyhow::Result<()> {
+        let dispatcher = self.settings().dispatcher.reload(config)?;
+        *self.settings.write().unwrap() = Arc::new(ServiceSettings::new(config, dispatcher));
+        Ok(())
+    }
+
+    /// Brings the state in line with the services and maintenance windows
+    /// of a reloaded `config`.
+    ///
+    /// Status, history and trackers of checks that are still configured are
+    /// kept; those of removed services and checks are dropped and open
+    /// incidents of removed services are closed without an alert. Flap
+    /// detection restarts from recent history if its settings changed.
+    pub async fn reload_services(&self, config: &Config) {
+        self.maintenance
+            .write()
+            .await
+            .replace_configured(config_maintenance(config));
+
+        let configured = |name: &str, check_type: &str| {
+            config.services.iter().any(|s| {
+                s.name == name && s.checks.iter().any(|c| c.check_type.label() == check_type)
+            })
+        };
+
+        let mut services = self.services.write().await;
+        services.retain(|name, _| config.services.iter().any(|s| &s.name == name));
+        {
+            let history = self.history.read().await;
+            let mut detectors = self.flapping.write().await;
+            for service_config in &config.services {
+                let Some(service) = services.get_mut(&service_config.name) else {
+                    continue;
+                };
+                service.url = service_config.url.clone();
+                service
+                    .checks
+                    .retain(|c| configured(&service_config.name, &c.check_type));
+
+                for check_config in &service_config.checks {
+                    let check_type = check_config.check_type.label();
+                    let Some(check) = service
+                        .checks
+                        .iter_mut()
+                        .find(|c| c.check_type == check_type)
+                    else {
+                        continue;
+                    };
+                    check.interval_seconds = check_config.interval_seconds;
+
+                    let key = (service_config.name.clone(), check_type.to_string());
+                    let fresh = flap_detector(check_config);
+                    let unchanged = detectors.get(&key).is_some_and(|d| d.same_settings(&fresh));
+                    if check_config.flap_detection && unchanged {
+                        continue;
+                    }
+                    if let Some(settled) = check.settled_status.take() {
+                        check.status = settled;
+                    }
+                    check.state_change_percentage = 0.0;
+                    detectors.remove(&key);
+                    if check_config.flap_detection {
+                        let recent = history.get(&key).into_iter().flatten();
+                        let detector = replay_flap_detector(check, check_config, recent);
+                        detectors.insert(key, detector);
+                    }
+                }
+                recompute_service_aggregate(service);
+            }
+            detectors.retain(|(name, check_type), _| configured(name, check_type));
+        }
+        self.history
+            .write()
+            .await
+            .retain(|(name, check_type), _| configured(name, check_type));
+        self.uptime
+            .write()
+            .await
+            .retain(|(name, check_type), _| configured(name, check_type));
+        self.latency
+            .write()
+            .await
+            .retain(|(name, check_type), _| configured(name, check_type));
+        drop(services);
+
+        let now = Utc::now();
+        let mut log = self.incidents.write().await;
+        for incident in log
+            .incidents
+            .iter_mut()
+            .filter(|i| i.is_open() && !config.services.iter().any(|s| s.name == i.service_name))
+        {
+            incident.close(now);
+            println!(
+                "Incident #{} closed: {} was removed from the config",
+                incident.id, incident.service_name
+            );
+            if let Some(store) = &self.store {
+                store.record_incident(incident);
+            }
+        }
+    }
+
+    fn uptime_retention(&self) -> Duration {
+        longest(&self.uptime_windows)
+    }
+
+    fn latency_retention(&self) -> Duration {
+        longest(&self.latency_windows)
+    }
+
+    /// Reloads counters and the latest result of every configured check from
+    /// the store, so uptime survives restarts.
+    pub async fn restore(&self, config: &Config) -> anyhow::Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let stored = store.load()?;
+
+        let (incidents, last_id) = store.load_incidents(Utc::now() - self.incident_retention)?;
+        *self.incidents.write().await = IncidentLog {
+            incidents: incidents.into(),
+            last_id,
+        };
+
+        let mut maintenance = config_maintenance(config);
+        for window in store.load_maintenance()? {
+            match Maintenance::new(window) {
+                Ok(window) => maintenance.push(window),
+                Err(e) => println!("Skipping stored maintenance window: {}", e),
+            }
+        }
+        *self.maintenance.write().await = MaintenanceSchedule::new(maintenance);
+
+        let mut services = self.services.write().await;
+        for service_config in &config.services {
+            for check_config in &service_config.checks {
+                let check_type = check_config.check_type.label();
+                let Some(stored) = stored
+                    .iter()
+                    .find(|s| s.service == service_config.name && s.check_type == check_type)
+                else {
+                    continue;
+                };
+
+                let service = services
+                    .entry(service_config.name.clone())
+                    .or_insert_with(|| {
+                        ServiceStatus::new(service_config.name.clone(), service_config.url.clone())
+                    });
+                let last_check = stored.last.as_ref().map_or_else(Utc::now, |l| l.timestamp);
+                let check = check_entry(
+                    service,
+                    check_type,
+                    check_config.interval_seconds,
+                    last_check,
+                );
+
+                check.total_checks = stored.total_checks;
+                check.successful_checks = stored.successful_checks;
+                check.uptime_percentage = if check.total_checks > 0 {
+                    (check.successful_checks as f64 / check.total_checks as f64) * 100.0
+                } else {
+                    0.0
+                };
+                if let Some(last) = &stored.last {
+                    check.status = last.status.clone();
+                    check.response_time_ms = last.response_time_ms;
+                    check.message = last.message.clone();
+                }
+
+                let recent = store.history(
+                    &service_config.name,
+                    check_type,
+                    None,
+                    None,
+                    self.history_limit,
+                )?;
+                if check_config.flap_detection {
+                    let detector = replay_flap_detector(check, check_config, recent.iter());
+                    self.flapping.write().await.insert(
+                        (service_config.name.clone(), check_type.to_string()),
+                        detector,
+                    );
+                }
+
+                recompute_service_aggregate(service);
+
+                self.history.write().await.insert(
+                    (service_config.name.clone(), check_type.to_string()),
+                    recent.into(),
+                );
+
+                let max_gap = uptime_max_gap(check_config);
+                let retention = self.uptime_retention();
+                let buckets = store.uptime_buckets(
+                    &service_config.name,
+                    check_type,
+                    Utc::now() - retention,
+                    max_gap,
+                )?;
+                let last = stored
+                    .last
+                    .as_ref()
+                    .filter(|l| !l.maintenance)
+                    .map(|l| (l.timestamp, l.success));
+                self.uptime.write().await.insert(
+                    (service_config.name.clone(), check_type.to_string()),
+                    UptimeTracker::restore(max_gap, retention, buckets, last),
+                );
+
+                let retention = self.latency_retention();
+                let mut latency = LatencyTracker::new(retention);
+                for (at, ms) in store.response_times(
+                    &service_config.name,
+                    check_type,
+                    Utc::now() - retention,
+                )? {
+                    latency.record(at, ms);
+                }
+                self.latency.write().await.insert(
+                    (service_config.name.clone(), check_type.to_string()),
+                    latency,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn push_history(&self, name: &str, check_type: &str, record: CheckRecord) {
+        let mut history = self.history.write().await;
+        let results = history
+            .entry((name.to_string(), check_type.to_string()))
+            .or_default();
+        results.push_back(record);
+        while results.len() > self.history_limit {
+            results.pop_front();
         }
     }
 
+    /// Results of one check between `since` and `until`, oldest first.
+    ///
+    /// Served from the in-memory buffer when it covers the range, i.e. `since`
+    /// is set and not before the oldest buffered result, otherwise from the
+    /// store. Returns `None` for an unknown service or check; the check type
+    /// is matched case-insensitively.
+    pub async fn check_history(
+        &self,
+        name: &str,
+        check_type: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Option<anyhow::Result<Vec<CheckRecord>>> {
+        let check_type = {
+            let services = self.services.read().await;
+            services
+                .get(name)?
+                .checks
+                .iter()
+                .find(|c| c.check_type.eq_ignore_ascii_case(check_type))?
+                .check_type
+                .clone()
+        };
+
+        let in_range = |r: &CheckRecord| {
+            since.is_none_or(|since| r.timestamp >= since)
+                && until.is_none_or(|until| r.timestamp <= until)
+        };
+
+        {
+            let history = self.history.read().await;
+            let buffered = history.get(&(name.to_string(), check_type.clone()));
+            // Without `since` the range reaches back past any buffer.
+            let covered = match (since, buffered.and_then(|b| b.front())) {
+                (Some(since), Some(oldest)) => oldest.timestamp <= since,
+                _ => false,
+            };
+
+            if covered || self.store.is_none() {
+                let results = buffered
+                    .map(|b| b.iter().filter(|r| in_range(r)).cloned().collect())
+                    .unwrap_or_default();
+                return Some(Ok(results));
+            }
+        }
+
+        let store = self.store.clone()?;
+        let name = name.to_string();
+        let results = tokio::task::spawn_blocking(move || {
+            store.history(&name, &check_type, since, until, MAX_HISTORY_RESULTS)
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r);
+        Some(results)
+    }
+
     pub async fn update_check_status(
         &self,
         name: String,
         url: String,
         check_type: String,
-        is_success: bool,
-        response_time_ms: Option<u64>,
-        message: String,
-        interval_seconds: u64,
+        result: CheckResult,
+        config: &CheckConfig,
+    ) {
+        self.update_check_status_at(name, url, check_type, result, config, Utc::now())
+            .await;
+    }
+
+    /// Applies a check result taken at `now`.
+    pub async fn update_check_status_at(
+        &self,
+        name: String,
+        url: String,
+        check_type: String,
+        result: CheckResult,
+        config: &CheckConfig,
+        now: DateTime<Utc>,
     ) {
+        let settings = self.settings();
+        let maintenance = self.active_maintenance(&settings, &name, now).await;
+        let result = apply_latency_thresholds(result, config);
+
         let mut services = self.services.write().await;
+        let failed_dependency = failed_dependency(&services, &settings.dependencies, &name);
 
-        let service = services.entry(name.clone()).or_insert(ServiceStatus {
-            name: name.clone(),
-            url: url.clone(),
-            status: HealthStatus::Unknown,
-            last_check: Utc::now(),
-            response_time_ms: None,
-            uptime_percentage: 0.0,
-            total_checks: 0,
-            successful_checks: 0,
-            message: String::new(),
-            checks: Vec::new(),
-        });
+        let service = services
+            .entry(name.clone())
+            .or_insert_with(|| ServiceStatus::new(name.clone(), url.clone()));
 
         // keep URL up to date in case config changed
         service.url = url;
+        let was_flapping = service.status == HealthStatus::Flapping;
 
-        let now = Utc::now();
+        let check = check_entry(service, &check_type, config.interval_seconds, now);
 
-        let check = match service
-            .checks
-            .iter_mut()
-            .find(|c| c.check_type == check_type)
+        let record = CheckRecord {
+            timestamp: now,
+            status: result.status.clone(),
+            success: result.status.is_success(),
+            response_time_ms: result.response_time_ms,
+            message: result.message.clone(),
+            maintenance: maintenance.is_some(),
+        };
+        if let Some(store) = &self.store {
+            store.record(&name, &check_type, &record);
+        }
+        self.push_history(&name, &check_type, record).await;
         {
-            Some(existing) => existing,
-            None => {
-                service.checks.push(CheckStatus {
-                    check_type: check_type.clone(),
-                    status: HealthStatus::Unknown,
-                    last_check: now,
-                    response_time_ms: None,
-                    uptime_percentage: 0.0,
-                    total_checks: 0,
-                    successful_checks: 0,
-                    message: String::new(),
-                    interval_seconds,
+            let mut uptime = self.uptime.write().await;
+            let tracker = uptime
+                .entry((name.clone(), check_type.clone()))
+                .or_insert_with(|| {
+                    UptimeTracker::new(uptime_max_gap(config), self.uptime_retention())
                 });
-                service
-                    .checks
-                    .iter_mut()
-                    .find(|c| c.check_type == check_type)
-                    .expect("just inserted")
+            if maintenance.is_some() {
+                tracker.pause(now);
+            } else {
+                tracker.record(now, result.status.is_success());
             }
-        };
+        }
+        if let Some(ms) = result.response_time_ms {
+            self.latency
+                .write()
+                .await
+                .entry((name.clone(), check_type.clone()))
+                .or_insert_with(|| LatencyTracker::new(self.latency_retention()))
+                .record(now, ms);
+        }
 
-        check.total_checks += 1;
-        if is_success {
-            check.successful_checks += 1;
-            check.status = HealthStatus::Up;
+        if maintenance.is_none() {
+            check.total_checks += 1;
+            if result.status.is_success() {
+                check.successful_checks += 1;
+            }
+        }
+        if result.status.is_success() {
+            check.consecutive_successes += 1;
+            check.consecutive_failures = 0;
         } else {
-            check.status = HealthStatus::Down;
+            check.consecutive_failures += 1;
+            check.consecutive_successes = 0;
         }
 
+        if let Some(settled) = check.settled_status.take() {
+            check.status = settled;
+        }
+        apply_thresholds(check, result.status.clone(), config);
+        if config.flap_detection {
+            let mut detectors = self.flapping.write().await;
+            let detector = detectors
+                .entry((name.clone(), check_type.clone()))
+                .or_insert_with(|| flap_detector(config));
+            let flapping = detector.record(result.status.is_success());
+            set_flapping(check, flapping, detector.percentage());
+        }
         check.last_check = now;
-        check.response_time_ms = response_time_ms;
-        check.message = message;
-        check.interval_seconds = interval_seconds;
+        check.response_time_ms = result.response_time_ms;
+        check.message = result.message;
+        check.interval_seconds = config.interval_seconds;
+        check.certificate = result.certificate;
+        check.timings = result.timings;
         check.uptime_percentage = if check.total_checks > 0 {
             (check.successful_checks as f64 / check.total_checks as f64) * 100.0
         } else {
@@ -125,11 +735,592 @@ impl AppState {
         };
 
         recompute_service_aggregate(service);
+        // Incidents neither open nor close while impacted or in maintenance,
+        // so no alerts go out.
+        if let (
+            HealthStatus::Down | HealthStatus::Flapping | HealthStatus::Degraded,
+            Some(dependency),
+        ) = (&service.status, failed_dependency)
+        {
+            service.status = HealthStatus::Impacted;
+            service.message = format!("Impacted by {}: {}", dependency, service.message);
+        }
+        if let Some(window) = maintenance {
+            service.status = HealthStatus::Maintenance;
+            service.message = format!("Maintenance: {}", window);
+        }
+        self.track_incident(service, &check_type, was_flapping, now)
+            .await;
+    }
+
+    /// Name of the maintenance window covering `service` at `now`, if any.
+    async fn active_maintenance(
+        &self,
+        settings: &ServiceSettings,
+        service: &str,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        let tags = settings
+            .service_tags
+            .get(service)
+            .map_or(&[][..], Vec::as_slice);
+        self.maintenance
+            .read()
+            .await
+            .active(service, tags, now)
+            .map(|m| m.window.name.clone())
+    }
+
+    /// All maintenance windows, with whether each is active at `now`.
+    pub async fn maintenance_windows(&self, now: DateTime<Utc>) -> Vec<MaintenanceStatus> {
+        self.maintenance
+            .read()
+            .await
+            .windows()
+            .iter()
+            .map(|m| MaintenanceStatus {
+                window: m.window.clone(),
+                active: m.is_active(now),
+            })
+            .collect()
+    }
+
+    /// Validates and stores a maintenance window created through the API.
+    pub async fn add_maintenance(
+        &self,
+        window: MaintenanceWindow,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<MaintenanceWindow> {
+        let mut schedule = self.maintenance.write().await;
+        schedule.prune(now - self.incident_retention);
+        let window = schedule.add(window)?;
+        println!(
+            "Maintenance window #{} added: {}",
+            window.id.unwrap_or_default(),
+            window.name
+        );
+        if let Some(store) = &self.store {
+            let ends_at = schedule.windows().last().and_then(Maintenance::ends_at);
+            store.record_maintenance(&window, ends_at);
+        }
+        Ok(window)
+    }
+
+    /// Removes a maintenance window created through the API.
+    pub async fn remove_maintenance(&self, id: u64) -> Option<MaintenanceWindow> {
+        let window = self.maintenance.write().await.remove(id)?;
+        println!("Maintenance window #{} removed: {}", id, window.name);
+        if let Some(store) = &self.store {
+            store.delete_maintenance(id);
+        }
+        Some(window)
+    }
+
+    /// Opens, updates or closes the incident of `service` after its status
+    /// was recomputed following a result of `check_type`, and alerts when an
+    /// incident opens or closes, once when the service starts flapping, and
+    /// again if it stops flapping while still down.
+    async fn track_incident(
+        &self,
+        service: &ServiceStatus,
+        check_type: &str,
+        was_flapping: bool,
+        now: DateTime<Utc>,
+    ) {
+        let flapping = service.status == HealthStatus::Flapping;
+        let mut log = self.incidents.write().await;
+        let last_id = log.last_id;
+        let open = log
+            .incidents
+            .iter_mut()
+            .rev()
+            .find(|i| i.service_name == service.name && i.is_open());
+
+        let degraded = service.status == HealthStatus::Degraded;
+        let was_degraded = open.as_deref().is_some_and(|i| i.degraded);
+
+        // The changed incident and whether it was just opened or closed.
+        let changed = match open {
+            None if service.status == HealthStatus::Down || flapping || degraded => {
+                let mut incident = Incident::open(
+                    last_id + 1,
+                    service.name.clone(),
+                    now,
+                    service.message.clone(),
+                );
+                incident.degraded = degraded;
+                record_failures(&mut incident, service);
+                println!(
+                    "Incident #{} opened for {}: {}",
+                    incident.id, service.name, incident.message
+                );
+                log.last_id = incident.id;
+                log.incidents.push_back(incident);
+                log.incidents.back_mut().map(|i| (i, true))
+            }
+            // Transitions are suppressed while flapping; only its start alerts,
+            // and its end if the service is still down.
+            Some(incident) if service.status == HealthStatus::Down || flapping => {
+                // A degraded service going down alerts again and escalates
+                // from now on.
+                if was_degraded {
+                    incident.degraded = false;
+                    incident.down_at = Some(now);
+                }
+                let before = (incident.failing_checks.len(), incident.messages.len());
+                record_failures(incident, service);
+                let after = (incident.failing_checks.len(), incident.messages.len());
+                let transition = flapping != was_flapping || was_degraded;
+                (transition || before != after).then_some((incident, transition))
+            }
+            Some(incident) if degraded && incident.degraded => {
+                let before = (incident.failing_checks.len(), incident.messages.len());
+                record_failures(incident, service);
+                let after = (incident.failing_checks.len(), incident.messages.len());
+                (before != after).then_some((incident, false))
+            }
+            // A down service that comes back degraded closes its incident; the
+            // next result opens a degraded one.
+            Some(incident) if service.status.is_success() => {
+                incident.close(now);
+                println!(
+                    "Incident #{} resolved for {} after {}s",
+                    incident.id,
+                    service.name,
+                    incident.duration_seconds.unwrap_or_default()
+                );
+                Some((incident, true))
+            }
+            _ => None,
+        };
+
+        if let Some((incident, transition)) = changed {
+            if transition {
+                let response_time_ms = service
+                    .checks
+                    .iter()
+                    .find(|c| c.check_type == check_type)
+                    .and_then(|c| c.response_time_ms);
+                let mut event =
+                    AlertEvent::from_incident(incident, &service.url, check_type, response_time_ms);
+                if flapping {
+                    event.kind = AlertKind::Flapping;
+                }
+                if incident.is_open() && (flapping || was_flapping || was_degraded) {
+                    event.message = service.message.clone();
+                    event.timestamp = now;
+                }
+                for name in self
+                    .settings()
+                    .dispatcher
+                    .dispatch(event, &incident.notified)
+                {
+                    if !incident.notified.contains(&name) {
+                        incident.notified.push(name);
+                    }
+                }
+            }
+            if let Some(store) = &self.store {
+                store.record_incident(incident);
+            }
+        }
+
+        let cutoff = now - self.incident_retention;
+        while log
+            .incidents
+            .front()
+            .is_some_and(|i| i.ended_at.is_some_and(|ended| ended < cutoff))
+        {
+            log.incidents.pop_front();
+        }
+    }
+
+    /// Alerts the next tiers of the escalation policy for every open,
+    /// unacknowledged incident whose service has been down longer than their
+    /// delay.
+    /// Tier notifiers are added to `notified` so they also get the recovery.
+    pub async fn escalate_incidents(&self, now: DateTime<Utc>) {
+        let settings = self.settings();
+        let urls: HashMap<String, String> = self
+            .services
+            .read()
+            .await
+            .values()
+            .filter(|s| !matches!(s.status, HealthStatus::Maintenance | HealthStatus::Impacted))
+            .map(|s| (s.name.clone(), s.url.clone()))
+            .collect();
+
+        let mut log = self.incidents.write().await;
+        for incident in log
+            .incidents
+            .iter_mut()
+            .filter(|i| i.is_open() && !i.degraded && i.acknowledged_at.is_none())
+        {
+            // Impacted services and those in maintenance are left out of `urls`.
+            let (Some(policy), Some(url)) = (
+                settings.escalations.get(&incident.service_name),
+                urls.get(&incident.service_name),
+            ) else {
+                continue;
+            };
+
+            let level = incident.escalation_level;
+            while let Some(tier) = policy.tiers.get(incident.escalation_level) {
+                if now - incident.down_since() < Duration::minutes(tier.after_minutes as i64) {
+                    break;
+                }
+                incident.escalation_level += 1;
+                println!(
+                    "Incident #{} for {} escalated to level {}",
+                    incident.id, incident.service_name, incident.escalation_level
+                );
+
+                settings
+                    .dispatcher
+                    .send_to(AlertEvent::escalation(incident, url, now), &tier.notifiers);
+                for name in &tier.notifiers {
+                    if !incident.notified.contains(name) {
+                        incident.notified.push(name.clone());
+                    }
+                }
+            }
+
+            if incident.escalation_level != level {
+                if let Some(store) = &self.store {
+                    store.record_incident(incident);
+                }
+            }
+        }
+    }
+
+    /// Acknowledges incident `id`, which stops its escalation. Acknowledging
+    /// it again keeps the first acknowledgement.
+    pub async fn acknowledge_incident(
+        &self,
+        id: u64,
+        by: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<Incident, AckError> {
+        let mut log = self.incidents.write().await;
+        let incident = log
+            .incidents
+            .iter_mut()
+            .find(|i| i.id == id)
+            .ok_or(AckError::NotFound)?;
+        if !incident.is_open() {
+            return Err(AckError::Resolved);
+        }
+
+        if incident.acknowledged_at.is_none() {
+            incident.acknowledge(now, by);
+            println!(
+                "Incident #{} for {} acknowledged{}",
+                incident.id,
+                incident.service_name,
+                incident
+                    .acknowledged_by
+                    .as_ref()
+                    .map(|by| format!(" by {}", by))
+                    .unwrap_or_default()
+            );
+            if let Some(store) = &self.store {
+                store.record_incident(incident);
+            }
+        }
+        Ok(incident.clone())
+    }
+
+    /// Incidents overlapping the range between `since` and `until`, newest
+    /// first, optionally only those of `service`.
+    pub async fn incidents(
+        &self,
+        service: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Vec<Incident> {
+        let log = self.incidents.read().await;
+        log.incidents
+            .iter()
+            .rev()
+            .filter(|i| service.is_none_or(|s| i.service_name == s))
+            .filter(|i| i.overlaps(since, until))
+            .cloned()
+            .collect()
+    }
+
+    /// Recent alert deliveries, newest first, optionally only failed ones.
+    pub fn alert_deliveries(&self, failed_only: bool) -> Vec<Delivery> {
+        self.settings()
+            .dispatcher
+            .deliveries()
+            .into_iter()
+            .filter(|d| !failed_only || !d.success)
+            .collect()
     }
 
     pub async fn get_all_services(&self) -> Vec<ServiceStatus> {
         let services = self.services.read().await;
-        services.values().cloned().collect()
+        let uptime = self.uptime.read().await;
+        let latency = self.latency.read().await;
+        let settings = self.settings();
+        let now = Utc::now();
+
+        services
+            .values()
+            .cloned()
+            .map(|mut service| {
+                service.depends_on = settings
+                    .dependencies
+                    .get(&service.name)
+                    .cloned()
+                    .unwrap_or_default();
+                let mut service_samples = vec![UptimeSample::default(); self.uptime_windows.len()];
+
+                for check in &mut service.checks {
+                    let key = (service.name.clone(), check.check_type.clone());
+                    let tracker = uptime.get(&key);
+                    check.uptime_windows = self
+                        .uptime_windows
+                        .iter()
+                        .zip(service_samples.iter_mut())
+                        .map(|(window, service_sample)| {
+                            let sample = tracker
+                                .map(|t| t.sample(window.duration(), now))
+                                .unwrap_or_default();
+                            service_sample.add(sample);
+                            WindowUptime {
+                                window: window.label().to_string(),
+                                uptime_percentage: sample.percentage(),
+                            }
+                        })
+                        .collect();
+
+                    let latency = latency.get(&key);
+                    check.latency = self
+                        .latency_windows
+                        .iter()
+                        .map(|window| {
+                            let sketch = latency
+                                .map(|t| t.sketch(window.duration(), now))
+                                .unwrap_or_default();
+                            WindowLatency {
+                                window: window.label().to_string(),
+                                samples: sketch.count(),
+                                summary: sketch.summary(),
+                            }
+                        })
+                        .collect();
+                }
+
+                // Like `uptime_percentage`, the service figure pools the time of all its checks.
+                service.uptime_windows = self
+                    .uptime_windows
+                    .iter()
+                    .zip(service_samples)
+                    .map(|(window, sample)| WindowUptime {
+                        window: window.label().to_string(),
+                        uptime_percentage: sample.percentage(),
+                    })
+                    .collect();
+                service
+            })
+            .collect()
+    }
+}
+
+fn record_failures(incident: &mut Incident, service: &ServiceStatus) {
+    let degraded = incident.degraded;
+    for check in service.checks.iter().filter(|c| match degraded {
+        true => matches!(
+            c.status,
+            HealthStatus::Degraded | HealthStatus::ExpiringSoon
+        ),
+        false => matches!(c.status, HealthStatus::Down | HealthStatus::Flapping),
+    }) {
+        incident.record_failure(check.last_check, &check.check_type, &check.message);
+    }
+}
+
+/// A service that `service` depends on, directly or through other
+/// dependencies, that is down.
+///
+/// Looking past direct dependencies means a dependent is impacted even when
+/// the service in between has not failed its own checks yet.
+fn failed_dependency(
+    services: &HashMap<String, ServiceStatus>,
+    dependencies: &HashMap<String, Vec<String>>,
+    service: &str,
+) -> Option<String> {
+    let mut pending: Vec<&String> = dependencies.get(service)?.iter().collect();
+    let mut seen = Vec::new();
+    while let Some(name) = pending.pop() {
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name);
+        if services.get(name).is_some_and(|s| {
+            matches!(
+                s.status,
+                HealthStatus::Down | HealthStatus::Impacted | HealthStatus::Flapping
+            )
+        }) {
+            return Some(name.clone());
+        }
+        pending.extend(dependencies.get(name).into_iter().flatten());
+    }
+    None
+}
+
+/// Maintenance windows from the config file; they were validated on load.
+fn config_maintenance(config: &Config) -> Vec<Maintenance> {
+    config
+        .maintenance_windows
+        .iter()
+        .filter_map(|w| Maintenance::new(w.clone()).ok())
+        .collect()
+}
+
+fn longest(windows: &[TimeWindow]) -> Duration {
+    windows
+        .iter()
+        .map(|w| w.duration())
+        .max()
+        .unwrap_or_default()
+}
+
+/// Longest time a single result is assumed to hold; anything beyond that
+/// (e.g. while the monitor was stopped) is not counted towards uptime.
+fn uptime_max_gap(config: &CheckConfig) -> Duration {
+    Duration::seconds(config.interval_seconds.max(1) as i64 * 2)
+}
+
+/// Returns the status entry for `check_type`, creating it if needed.
+fn check_entry<'a>(
+    service: &'a mut ServiceStatus,
+    check_type: &str,
+    interval_seconds: u64,
+    now: DateTime<Utc>,
+) -> &'a mut CheckStatus {
+    match service
+        .checks
+        .iter()
+        .position(|c| c.check_type == check_type)
+    {
+        Some(index) => &mut service.checks[index],
+        None => {
+            service.checks.push(CheckStatus {
+                check_type: check_type.to_string(),
+                status: HealthStatus::Unknown,
+                last_check: now,
+                response_time_ms: None,
+                uptime_percentage: 0.0,
+                uptime_windows: Vec::new(),
+                latency: Vec::new(),
+                total_checks: 0,
+                successful_checks: 0,
+                message: String::new(),
+                interval_seconds,
+                consecutive_failures: 0,
+                consecutive_successes: 0,
+                pending_status: None,
+                state_change_percentage: 0.0,
+                settled_status: None,
+                certificate: None,
+                timings: None,
+            });
+            service.checks.last_mut().expect("just inserted")
+        }
+    }
+}
+
+/// Flap detector for `check` replayed over its `recent` results, oldest
+/// first, with the check's status updated to match.
+fn replay_flap_detector<'a>(
+    check: &mut CheckStatus,
+    config: &CheckConfig,
+    recent: impl DoubleEndedIterator<Item = &'a CheckRecord>,
+) -> FlapDetector {
+    let mut detector = flap_detector(config);
+    let mut last: Vec<&CheckRecord> = recent.rev().take(config.flap_history).collect();
+    last.reverse();
+    let flapping = last.iter().fold(false, |_, r| detector.record(r.success));
+    set_flapping(check, flapping, detector.percentage());
+    detector
+}
+
+/// Turns a working result into `Degraded` or `Down` when its response time
+/// reaches the check's warning or critical threshold.
+fn apply_latency_thresholds(mut result: CheckResult, config: &CheckConfig) -> CheckResult {
+    let Some(ms) = result
+        .response_time_ms
+        .filter(|_| result.status.is_success())
+    else {
+        return result;
+    };
+
+    if let Some(critical) = config.response_time_critical_ms.filter(|c| ms >= *c) {
+        result.status = HealthStatus::Down;
+        result.message = format!(
+            "Response time {}ms reached the critical threshold of {}ms",
+            ms, critical
+        );
+    } else if let Some(warning) = config.response_time_warning_ms.filter(|w| ms >= *w) {
+        if result.status == HealthStatus::Up {
+            result.status = HealthStatus::Degraded;
+            result.message = format!(
+                "Response time {}ms reached the warning threshold of {}ms",
+                ms, warning
+            );
+        }
+    }
+    result
+}
+
+fn flap_detector(config: &CheckConfig) -> FlapDetector {
+    FlapDetector::new(
+        config.flap_history,
+        config.flap_low_threshold,
+        config.flap_high_threshold,
+    )
+}
+
+/// Shows a flapping check as `Flapping`, keeping its thresholded status for
+/// the next result.
+fn set_flapping(check: &mut CheckStatus, flapping: bool, percentage: f64) {
+    check.state_change_percentage = (percentage * 10.0).round() / 10.0;
+    if flapping {
+        check.settled_status = Some(std::mem::replace(&mut check.status, HealthStatus::Flapping));
+    }
+}
+
+/// Moves the check to `observed` once enough consecutive results agree.
+///
+/// Flipping between working (`Up`, `Degraded`, `ExpiringSoon`) and `Down` needs
+/// `failure_threshold` consecutive failures or `recovery_threshold`
+/// consecutive successes; until then the old status is kept and the target is
+/// exposed as `pending_status`. The first result after startup applies
+/// immediately.
+fn apply_thresholds(check: &mut CheckStatus, observed: HealthStatus, config: &CheckConfig) {
+    let flips =
+        check.status != HealthStatus::Unknown && check.status.is_success() != observed.is_success();
+
+    if !flips {
+        check.status = observed;
+        check.pending_status = None;
+        return;
+    }
+
+    let (count, threshold) = if observed.is_success() {
+        (check.consecutive_successes, config.recovery_threshold)
+    } else {
+        (check.consecutive_failures, config.failure_threshold)
+    };
+
+    if count >= threshold.max(1) {
+        check.status = observed;
+        check.pending_status = None;
+    } else {
+        check.pending_status = Some(observed);
     }
 }
 
@@ -149,12 +1340,21 @@ fn recompute_service_aggregate(service: &mut ServiceStatus) {
         .checks
         .iter()
         .any(|c| c.status == HealthStatus::Down);
+    let any_flapping = service
+        .checks
+        .iter()
+        .any(|c| c.status == HealthStatus::Flapping);
     let all_up = service.checks.iter().all(|c| c.status == HealthStatus::Up);
+    let all_working = service.checks.iter().all(|c| c.status.is_success());
 
     service.status = if any_down {
         HealthStatus::Down
+    } else if any_flapping {
+        HealthStatus::Flapping
     } else if all_up {
         HealthStatus::Up
+    } else if all_working {
+        HealthStatus::Degraded
     } else {
         HealthStatus::Unknown
     };
@@ -188,15 +1388,618 @@ fn recompute_service_aggregate(service: &mut ServiceStatus) {
         )
     };
 
-    if service.status == HealthStatus::Down {
+    if matches!(
+        service.status,
+        HealthStatus::Down | HealthStatus::Degraded | HealthStatus::Flapping
+    ) {
+        // A degraded service lists all checks that are working but not `Up`.
         let parts: Vec<String> = service
             .checks
             .iter()
-            .filter(|c| c.status == HealthStatus::Down)
-            .map(|c| format!("{}: {}", c.check_type, c.message))
+            .filter(|c| match service.status {
+                HealthStatus::Degraded => c.status != HealthStatus::Up,
+                _ => c.status == service.status,
+            })
+            .map(|c| match c.status {
+                HealthStatus::Flapping => format!(
+                    "{}: flapping ({:.0}% state change)",
+                    c.check_type, c.state_change_percentage
+                ),
+                _ => format!("{}: {}", c.check_type, c.message),
+            })
             .collect();
         service.message = parts.join(" | ");
     } else {
         service.message.clear();
     }
 }
+
+#[cfg(test)]
+impl AppState {
+    /// State backed by a fresh store in the temp directory, named after the
+    /// test.
+    pub fn for_test(name: &str, config: &Config) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "service-monitor-{}-{}.db",
+            name,
+            std::process::id()
+        ));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        let store = Store::open(&path, config.history_retention_days).unwrap();
+        let dispatcher = Dispatcher::new(config).unwrap();
+        Self::with_store(store, dispatcher, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    fn record(timestamp: DateTime<Utc>, status: HealthStatus) -> CheckRecord {
+        CheckRecord {
+            timestamp,
+            success: status.is_success(),
+            status,
+            response_time_ms: Some(10),
+            message: String::new(),
+            maintenance: false,
+        }
+    }
+
+    /// Feeds `result` to the first check of `service` in `config`.
+    async fn report(state: &AppState, config: &Config, service: &str, result: CheckResult) {
+        report_at(state, config, service, 0, result, Utc::now()).await;
+    }
+
+    /// Feeds `result`, taken at `now`, to check `index` of `service` in `config`.
+    async fn report_at(
+        state: &AppState,
+        config: &Config,
+        service: &str,
+        index: usize,
+        result: CheckResult,
+        now: DateTime<Utc>,
+    ) {
+        let service = config.services.iter().find(|s| s.name == service).unwrap();
+        let check = &service.checks[index];
+        state
+            .update_check_status_at(
+                service.name.clone(),
+                service.url.clone(),
+                check.check_type.label().to_string(),
+                result,
+                check,
+                now,
+            )
+            .await;
+    }
+
+    fn up() -> CheckResult {
+        CheckResult::up(Some(10), "HTTP 200".to_string())
+    }
+
+    fn down() -> CheckResult {
+        CheckResult::down(Some(10), "HTTP 500".to_string())
+    }
+
+    /// A service `api` with an escalation policy of two tiers, alerting
+    /// notifiers that cannot be reached.
+    fn escalating_config() -> Config {
+        let hook = |port| {
+            serde_json::json!({
+                "type": "Webhook",
+                "url": format!("http://127.0.0.1:{}/", port),
+                "retries": 0
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "services": [{
+                "name": "api",
+                "url": "http://127.0.0.1",
+                "notifiers": ["chat"],
+                "escalation_policy": "api",
+                "checks": [{ "check_type": "Http", "interval_seconds": 30, "timeout_ms": 1000 }]
+            }],
+            "notifiers": { "chat": hook(1), "oncall": hook(2), "manager": hook(3) },
+            "escalation_policies": {
+                "api": {
+                    "tiers": [
+                        { "after_minutes": 15, "notifiers": ["oncall"] },
+                        { "after_minutes": 45, "notifiers": ["manager"] }
+                    ]
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Escalation level and notified notifiers of the latest incident.
+    async fn escalation(state: &AppState) -> (usize, Vec<String>) {
+        let incident = state.incidents(None, None, None).await.remove(0);
+        (incident.escalation_level, incident.notified)
+    }
+
+    /// Waits for the store's writer thread to persist `count` results.
+    async fn wait_for_results(store: &Store, service: &str, check_type: &str, count: usize) {
+        for _ in 0..200 {
+            let stored = store.history(service, check_type, None, None, MAX_HISTORY_RESULTS);
+            if stored.is_ok_and(|r| r.len() >= count) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("results were not persisted");
+    }
+
+    #[tokio::test]
+    async fn history_before_the_buffer_comes_from_the_store() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [{
+                "name": "api",
+                "url": "http://127.0.0.1",
+                "checks": [{ "check_type": "Http", "interval_seconds": 30, "timeout_ms": 1000 }]
+            }]
+        }))
+        .unwrap();
+        let state = AppState::for_test("history-until", &config);
+        let store = state.store.clone().unwrap();
+        let check_config = &config.services[0].checks[0];
+
+        let now = Utc::now();
+        for hours in [3, 2] {
+            store.record(
+                "api",
+                "HTTP",
+                &record(now - Duration::hours(hours), HealthStatus::Up),
+            );
+        }
+        state
+            .update_check_status(
+                "api".to_string(),
+                "http://127.0.0.1".to_string(),
+                "HTTP".to_string(),
+                CheckResult::up(Some(10), "OK".to_string()),
+                check_config,
+            )
+            .await;
+        wait_for_results(&store, "api", "HTTP", 3).await;
+
+        let until = now - Duration::hours(1);
+        let results = state
+            .check_history("api", "http", None, Some(until))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.timestamp <= until));
+
+        let since = now - Duration::minutes(1);
+        let results = state
+            .check_history("api", "http", Some(since), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn alerts_down_when_flapping_ends_while_down() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [{
+                "name": "api",
+                "url": "http://127.0.0.1",
+                "notifiers": ["hook"],
+                "checks": [{
+                    "check_type": "Http",
+                    "interval_seconds": 30,
+                    "timeout_ms": 1000,
+                    "flap_detection": true,
+                        "flap_history": 5
+                }]
+            }],
+            "notifiers": {
+                "hook": { "type": "Webhook", "url": "http://127.0.0.1:1/", "retries": 0 }
+            }
+        }))
+        .unwrap();
+        let state = AppState::for_test("flapping-down", &config);
+        let check_config = &config.services[0].checks[0];
+
+        // Opens an incident, flaps, then settles while down.
+        for up in [true, false, true, false, false, false, false] {
+            let result = match up {
+                true => CheckResult::up(Some(10), "HTTP 200".to_string()),
+                false => CheckResult::down(Some(10), "HTTP 500".to_string()),
+            };
+            state
+                .update_check_status(
+                    "api".to_string(),
+                    "http://127.0.0.1".to_string(),
+                    "HTTP".to_string(),
+                    result,
+                    check_config,
+                )
+                .await;
+        }
+        assert_eq!(
+            state.services.read().await["api"].status,
+            HealthStatus::Down
+        );
+
+        let mut deliveries = Vec::new();
+        for _ in 0..200 {
+            deliveries = state.alert_deliveries(false);
+            if deliveries.len() >= 3 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let kinds: Vec<AlertKind> = deliveries.iter().rev().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            [AlertKind::Down, AlertKind::Flapping, AlertKind::Down]
+        );
+        assert!(deliveries.iter().all(|d| d.incident_id == 1));
+    }
+
+    #[tokio::test]
+    async fn dependents_of_a_down_service_do_not_open_incidents_while_flapping() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [
+                {
+                    "name": "db",
+                    "url": "tcp://127.0.0.1:5432",
+                    "checks": [{ "check_type": "Tcp", "interval_seconds": 30, "timeout_ms": 1000 }]
+                },
+                {
+                    "name": "api",
+                    "url": "http://127.0.0.1",
+                    "depends_on": ["db"],
+                    "checks": [{
+                        "check_type": "Http",
+                        "interval_seconds": 30,
+                        "timeout_ms": 1000,
+                        "flap_detection": true,
+                        "flap_history": 5
+                    }]
+                }
+            ]
+        }))
+        .unwrap();
+        let state = AppState::for_test("impacted-flapping", &config);
+
+        report(&state, &config, "db", down()).await;
+        for up_result in [true, false, true, false, true] {
+            let result = if up_result { up() } else { down() };
+            report(&state, &config, "api", result).await;
+        }
+
+        let services = state.services.read().await;
+        assert_eq!(services["api"].checks[0].status, HealthStatus::Flapping);
+        assert_eq!(services["api"].status, HealthStatus::Impacted);
+        assert!(services["api"].message.starts_with("Impacted by db: "));
+        drop(services);
+        assert_eq!(state.incidents(Some("db"), None, None).await.len(), 1);
+        assert!(state.incidents(Some("api"), None, None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn incidents_open_collect_failures_and_close_on_recovery() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [{
+                "name": "api",
+                "url": "http://127.0.0.1",
+                "checks": [
+                    { "check_type": "Http", "interval_seconds": 30, "timeout_ms": 1000 },
+                    { "check_type": "Tcp", "interval_seconds": 30, "timeout_ms": 1000 }
+                ]
+            }]
+        }))
+        .unwrap();
+        let state = AppState::for_test("incident-lifecycle", &config);
+        let start = Utc::now() - Duration::minutes(10);
+        let at = |seconds| start + Duration::seconds(seconds);
+
+        report_at(&state, &config, "api", 0, up(), at(0)).await;
+        report_at(&state, &config, "api", 1, up(), at(0)).await;
+        assert!(state.incidents(None, None, None).await.is_empty());
+
+        report_at(&state, &config, "api", 0, down(), at(30)).await;
+        let refused = CheckResult::down(None, "Connection refused".to_string());
+        report_at(&state, &config, "api", 1, refused, at(60)).await;
+        report_at(&state, &config, "api", 0, down(), at(60)).await;
+
+        let incidents = state.incidents(Some("api"), None, None).await;
+        assert_eq!(incidents.len(), 1);
+        let incident = &incidents[0];
+        assert!(incident.is_open());
+        assert_eq!(incident.started_at, at(30));
+        assert_eq!(incident.message, "HTTP: HTTP 500");
+        assert_eq!(incident.failing_checks, ["HTTP", "TCP"]);
+        // The repeated HTTP failure is not recorded twice.
+        let messages: Vec<&str> = incident
+            .messages
+            .iter()
+            .map(|m| m.message.as_str())
+            .collect();
+        assert_eq!(messages, ["HTTP 500", "Connection refused"]);
+
+        // Still down while one check fails.
+        report_at(&state, &config, "api", 0, up(), at(90)).await;
+        assert!(state.incidents(None, None, None).await[0].is_open());
+
+        report_at(&state, &config, "api", 1, up(), at(150)).await;
+        let incidents = state.incidents(None, None, None).await;
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].ended_at, Some(at(150)));
+        assert_eq!(incidents[0].duration_seconds, Some(120));
+
+        // The next failure opens a new incident.
+        report_at(&state, &config, "api", 0, down(), at(180)).await;
+        let incidents = state.incidents(None, None, None).await;
+        assert_eq!(incidents.len(), 2);
+        assert_eq!((incidents[0].id, incidents[1].id), (2, 1));
+        assert!(incidents[0].is_open());
+
+        assert_eq!(state.incidents(None, Some(at(160)), None).await.len(), 1);
+        assert_eq!(state.incidents(None, None, Some(at(100))).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn maintenance_suppresses_incidents_and_pauses_uptime() {
+        let now = Utc::now();
+        let opens = now - Duration::minutes(5);
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [{
+                "name": "api",
+                "url": "http://127.0.0.1",
+                "checks": [{ "check_type": "Http", "interval_seconds": 30, "timeout_ms": 1000 }]
+            }],
+            "maintenance_windows": [{
+                "name": "deploy",
+                "services": ["api"],
+                "cron": format!("{} {} * * *", opens.minute(), opens.hour()),
+                "duration_minutes": 60
+            }]
+        }))
+        .unwrap();
+        let state = AppState::for_test("maintenance", &config);
+
+        report_at(&state, &config, "api", 0, up(), now - Duration::minutes(10)).await;
+        for minutes in [4, 3, 2] {
+            report_at(
+                &state,
+                &config,
+                "api",
+                0,
+                down(),
+                now - Duration::minutes(minutes),
+            )
+            .await;
+        }
+
+        let service = state.get_all_services().await.remove(0);
+        assert_eq!(service.status, HealthStatus::Maintenance);
+        assert_eq!(service.message, "Maintenance: deploy");
+        assert!(state.incidents(None, None, None).await.is_empty());
+        // Up until the window opened, then not counted.
+        let check = &service.checks[0];
+        assert_eq!(check.total_checks, 1);
+        assert_eq!(check.uptime_windows[0].uptime_percentage, Some(100.0));
+    }
+
+    #[tokio::test]
+    async fn unacknowledged_incidents_escalate_after_each_tier_delay() {
+        let config = escalating_config();
+        let state = AppState::for_test("escalation", &config);
+        let start = Utc::now() - Duration::hours(2);
+        let at = |minutes| start + Duration::minutes(minutes);
+
+        report_at(&state, &config, "api", 0, down(), at(0)).await;
+        assert_eq!(escalation(&state).await, (0, vec!["chat".to_string()]));
+
+        state.escalate_incidents(at(14)).await;
+        assert_eq!(escalation(&state).await.0, 0);
+
+        state.escalate_incidents(at(15)).await;
+        assert_eq!(
+            escalation(&state).await,
+            (1, vec!["chat".to_string(), "oncall".to_string()])
+        );
+
+        // A tier is alerted only once.
+        state.escalate_incidents(at(30)).await;
+        assert_eq!(escalation(&state).await.0, 1);
+
+        state.escalate_incidents(at(50)).await;
+        let (level, notified) = escalation(&state).await;
+        assert_eq!(level, 2);
+        assert_eq!(notified, ["chat", "oncall", "manager"]);
+
+        // Past the last tier nothing changes.
+        state.escalate_incidents(at(120)).await;
+        assert_eq!(escalation(&state).await.0, 2);
+    }
+
+    #[tokio::test]
+    async fn acknowledged_incidents_stop_escalating() {
+        let config = escalating_config();
+        let state = AppState::for_test("acknowledge", &config);
+        let start = Utc::now() - Duration::hours(2);
+        let at = |minutes| start + Duration::minutes(minutes);
+
+        report_at(&state, &config, "api", 0, down(), at(0)).await;
+        let id = state.incidents(None, None, None).await[0].id;
+        assert!(matches!(
+            state.acknowledge_incident(id + 1, None, at(1)).await,
+            Err(AckError::NotFound)
+        ));
+
+        let acked = state
+            .acknowledge_incident(id, Some("alice".to_string()), at(5))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(acked.acknowledged_at, Some(at(5)));
+        // Acknowledging again keeps the first acknowledgement.
+        let again = state
+            .acknowledge_incident(id, Some("bob".to_string()), at(6))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(again.acknowledged_by.as_deref(), Some("alice"));
+
+        state.escalate_incidents(at(60)).await;
+        assert_eq!(escalation(&state).await, (0, vec!["chat".to_string()]));
+
+        report_at(&state, &config, "api", 0, up(), at(61)).await;
+        assert!(matches!(
+            state.acknowledge_incident(id, None, at(62)).await,
+            Err(AckError::Resolved)
+        ));
+    }
+
+    #[test]
+    fn latency_thresholds_degrade_or_fail_working_results() {
+        let config: CheckConfig = serde_json::from_value(serde_json::json!({
+            "check_type": "Http",
+            "interval_seconds": 30,
+            "timeout_ms": 1000,
+            "response_time_warning_ms": 200,
+            "response_time_critical_ms": 500
+        }))
+        .unwrap();
+        let apply = |status, ms| {
+            let result = CheckResult::with_status(status, ms, "HTTP 200".to_string());
+            let result = apply_latency_thresholds(result, &config);
+            (result.status, result.message)
+        };
+
+        assert_eq!(
+            apply(HealthStatus::Up, Some(199)),
+            (HealthStatus::Up, "HTTP 200".to_string())
+        );
+        assert_eq!(
+            apply(HealthStatus::Up, Some(200)),
+            (
+                HealthStatus::Degraded,
+                "Response time 200ms reached the warning threshold of 200ms".to_string()
+            )
+        );
+        assert_eq!(
+            apply(HealthStatus::Up, Some(500)),
+            (
+                HealthStatus::Down,
+                "Response time 500ms reached the critical threshold of 500ms".to_string()
+            )
+        );
+        // An expiring certificate keeps its status below the critical threshold.
+        assert_eq!(
+            apply(HealthStatus::ExpiringSoon, Some(300)).0,
+            HealthStatus::ExpiringSoon
+        );
+        assert_eq!(
+            apply(HealthStatus::ExpiringSoon, Some(600)).0,
+            HealthStatus::Down
+        );
+        // Failures and results without a response time are left alone.
+        assert_eq!(
+            apply(HealthStatus::Down, Some(900)),
+            (HealthStatus::Down, "HTTP 200".to_string())
+        );
+        assert_eq!(apply(HealthStatus::Up, None).0, HealthStatus::Up);
+    }
+
+    #[tokio::test]
+    async fn expiring_certificates_degrade_the_service() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [{
+                "name": "site",
+                "url": "https://127.0.0.1",
+                "checks": [
+                    { "check_type": "Http", "interval_seconds": 30, "timeout_ms": 1000 },
+                    { "check_type": "Ssl", "interval_seconds": 30, "timeout_ms": 1000 }
+                ]
+            }]
+        }))
+        .unwrap();
+        let state = AppState::for_test("expiring-certificate", &config);
+        let now = Utc::now();
+
+        report_at(&state, &config, "site", 0, up(), now).await;
+        let expiring = CheckResult::with_status(
+            HealthStatus::ExpiringSoon,
+            Some(20),
+            "Certificate expires in 5 days".to_string(),
+        );
+        report_at(&state, &config, "site", 1, expiring, now).await;
+
+        let services = state.services.read().await;
+        assert_eq!(services["site"].status, HealthStatus::Degraded);
+        assert_eq!(
+            services["site"].message,
+            "SSL: Certificate expires in 5 days"
+        );
+        drop(services);
+        let incidents = state.incidents(None, None, None).await;
+        assert_eq!(incidents.len(), 1);
+        assert!(incidents[0].degraded);
+        assert_eq!(incidents[0].failing_checks, ["SSL"]);
+    }
+
+    #[tokio::test]
+    async fn degraded_incident_becomes_down_and_escalates_from_then() {
+        let mut config = escalating_config();
+        config.services[0].checks[0].response_time_warning_ms = Some(200);
+        let state = AppState::for_test("degraded-to-down", &config);
+        let start = Utc::now() - Duration::hours(2);
+        let at = |minutes| start + Duration::minutes(minutes);
+
+        let slow = CheckResult::up(Some(300), "HTTP 200".to_string());
+        report_at(&state, &config, "api", 0, slow, at(0)).await;
+        let incident = state.incidents(None, None, None).await.remove(0);
+        assert!(incident.degraded);
+        assert_eq!(
+            state.services.read().await["api"].status,
+            HealthStatus::Degraded
+        );
+
+        // Degraded incidents do not escalate.
+        state.escalate_incidents(at(60)).await;
+        assert_eq!(escalation(&state).await.0, 0);
+
+        report_at(&state, &config, "api", 0, down(), at(60)).await;
+        let incidents = state.incidents(None, None, None).await;
+        assert_eq!(incidents.len(), 1);
+        let down_incident = &incidents[0];
+        assert_eq!(down_incident.id, incident.id);
+        assert!(!down_incident.degraded);
+        assert_eq!(down_incident.started_at, at(0));
+        assert_eq!(down_incident.down_at, Some(at(60)));
+
+        // Tier delays count from when the service went down.
+        state.escalate_incidents(at(74)).await;
+        assert_eq!(escalation(&state).await.0, 0);
+        state.escalate_incidents(at(75)).await;
+        assert_eq!(escalation(&state).await.0, 1);
+
+        let mut kinds = Vec::new();
+        for _ in 0..200 {
+            kinds = state
+                .alert_deliveries(false)
+                .iter()
+                .filter(|d| d.notifier == "chat")
+                .rev()
+                .map(|d| d.kind)
+                .collect();
+            if kinds.len() >= 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(kinds, [AlertKind::Degraded, AlertKind::Down]);
+    }
+}
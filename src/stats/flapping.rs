@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+/// Weight of the oldest and the newest state change.
+const OLDEST_WEIGHT: f64 = 0.8;
+const NEWEST_WEIGHT: f64 = 1.2;
+
+/// Nagios-style flap detection for one check.
+///
+/// Keeps whether each of the last `history` results passed and computes the
+/// percentage of state changes among them, weighting recent changes more
+/// (0.8 for the oldest, 1.2 for the newest). A check starts flapping when the
+/// percentage reaches `high_threshold` and stops once it drops below
+/// `low_threshold`, so it does not toggle around a single threshold. Until
+/// `history` results were seen, the missing ones count as unchanged.
+pub struct FlapDetector {
+    states: VecDeque<bool>,
+    history: usize,
+    low_threshold: f64,
+    high_threshold: f64,
+    flapping: bool,
+    percentage: f64,
+}
+
+impl FlapDetector {
+    pub fn new(history: usize, low_threshold: f64, high_threshold: f64) -> Self {
+        Self {
+            states: VecDeque::with_capacity(history),
+            history: history.max(2),
+            low_threshold,
+            high_threshold,
+            flapping: false,
+            percentage: 0.0,
+        }
+    }
+
+    /// Records a result and returns whether the check is flapping.
+    pub fn record(&mut self, success: bool) -> bool {
+        self.states.push_back(success);
+        while self.states.len() > self.history {
+            self.states.pop_front();
+        }
+
+        // Changes are numbered so that the newest one always has index
+        // `history - 2`, even while the history is still filling up.
+        let offset = self.history - self.states.len();
+        let steps = (self.history - 2).max(1) as f64;
+        let changes: f64 = self
+            .states
+            .iter()
+            .zip(self.states.iter().skip(1))
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .fold(0.0, |sum, (i, _)| {
+                sum + OLDEST_WEIGHT + (NEWEST_WEIGHT - OLDEST_WEIGHT) * (i + offset) as f64 / steps
+            });
+        self.percentage = changes / (self.history - 1) as f64 * 100.0;
+
+        if self.flapping {
+            self.flapping = self.percentage >= self.low_threshold;
+        } else {
+            self.flapping = self.percentage >= self.high_threshold;
+        }
+        self.flapping
+    }
+
+    /// Weighted percentage of state changes, as of the latest result.
+    pub fn percentage(&self) -> f64 {
+        self.percentage
+    }
+
+    /// Whether `other` was created with the same history and thresholds.
+    pub fn same_settings(&self, other: &FlapDetector) -> bool {
+        self.history == other.history
+            && self.low_threshold == other.low_threshold
+            && self.high_threshold == other.high_threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_all(detector: &mut FlapDetector, states: &[bool]) -> Vec<bool> {
+        states.iter().map(|s| detector.record(*s)).collect()
+    }
+
+    fn assert_percentage(detector: &FlapDetector, expected: f64) {
+        let actual = detector.percentage();
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn steady_results_never_flap() {
+        let mut detector = FlapDetector::new(11, 25.0, 50.0);
+        assert!(!record_all(&mut detector, &[false; 30]).contains(&true));
+        assert_percentage(&detector, 0.0);
+    }
+
+    #[test]
+    fn alternating_results_flap() {
+        let mut detector = FlapDetector::new(11, 25.0, 50.0);
+        let states: Vec<bool> = (0..11).map(|i| i % 2 == 0).collect();
+        assert!(record_all(&mut detector, &states).last().unwrap());
+        assert_percentage(&detector, 100.0);
+    }
+
+    #[test]
+    fn recent_changes_weigh_more() {
+        let mut detector = FlapDetector::new(11, 25.0, 50.0);
+        record_all(&mut detector, &[true; 10]);
+        detector.record(false);
+        assert_percentage(&detector, 12.0);
+
+        record_all(&mut detector, &[false; 9]);
+        assert_percentage(&detector, 8.0);
+    }
+
+    #[test]
+    fn missing_history_counts_as_unchanged() {
+        let mut detector = FlapDetector::new(11, 25.0, 50.0);
+        record_all(&mut detector, &[true, false]);
+        assert_percentage(&detector, 12.0);
+        // Same as five alternations after a full history of successes.
+        assert!(record_all(&mut detector, &[true, false, true, false])[3]);
+        assert_percentage(&detector, 55.56);
+    }
+
+    #[test]
+    fn hysteresis_between_thresholds() {
+        let mut detector = FlapDetector::new(11, 25.0, 50.0);
+        record_all(&mut detector, &[true; 11]);
+
+        let flapping = record_all(&mut detector, &[false, true, false, true, false]);
+        assert_eq!(flapping, [false, false, false, false, true]);
+        assert_percentage(&detector, 55.56);
+
+        // Stays flapping below the high threshold until it drops under the low one.
+        let flapping = record_all(&mut detector, &[false; 8]);
+        assert_eq!(flapping, [true, true, true, true, true, true, true, false]);
+        assert_percentage(&detector, 16.44);
+    }
+
+    #[test]
+    fn same_settings() {
+        let detector = FlapDetector::new(11, 25.0, 50.0);
+        assert!(detector.same_settings(&FlapDetector::new(11, 25.0, 50.0)));
+        assert!(!detector.same_settings(&FlapDetector::new(21, 25.0, 50.0)));
+        assert!(!detector.same_settings(&FlapDetector::new(11, 20.0, 50.0)));
+        assert!(!detector.same_settings(&FlapDetector::new(11, 25.0, 60.0)));
+        // Histories below two are raised to two.
+        assert!(FlapDetector::new(0, 1.0, 2.0).same_settings(&FlapDetector::new(2, 1.0, 2.0)));
+    }
+}
@@ -0,0 +1,322 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+const MINUTE_MS: i64 = 60_000;
+const HOUR_MS: i64 = 3_600_000;
+
+/// Relative accuracy of reported percentiles (2%).
+const ALPHA: f64 = 0.02;
+
+/// Response time distribution with bounded memory.
+///
+/// Values are counted in logarithmic bins where each bin spans a factor of
+/// `(1 + ALPHA) / (1 - ALPHA)`, so any percentile is within `ALPHA` of the
+/// true value and the number of bins only grows with the log of the range
+/// (about 350 bins from 1ms to 20 minutes). Sketches merge by adding bins,
+/// which lets the tracker keep one per minute or hour and combine them per window.
+/// Count, min, max, mean and standard deviation are exact.
+#[derive(Clone, Default)]
+pub struct LatencySketch {
+    bins: BTreeMap<i32, u64>,
+    zeros: u64,
+    count: u64,
+    sum: f64,
+    sum_squares: f64,
+    min: u64,
+    max: u64,
+}
+
+/// Summary of a sketch as reported by the API, in milliseconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: f64,
+    pub stddev_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+fn gamma() -> f64 {
+    (1.0 + ALPHA) / (1.0 - ALPHA)
+}
+
+impl LatencySketch {
+    pub fn add(&mut self, ms: u64) {
+        if ms == 0 {
+            self.zeros += 1;
+        } else {
+            let bin = (ms as f64).ln() / gamma().ln();
+            *self.bins.entry(bin.ceil() as i32).or_default() += 1;
+        }
+
+        self.min = if self.count == 0 {
+            ms
+        } else {
+            self.min.min(ms)
+        };
+        self.max = self.max.max(ms);
+        self.count += 1;
+        self.sum += ms as f64;
+        self.sum_squares += (ms as f64) * (ms as f64);
+    }
+
+    pub fn merge(&mut self, other: &LatencySketch) {
+        if other.count == 0 {
+            return;
+        }
+        for (bin, count) in &other.bins {
+            *self.bins.entry(*bin).or_default() += count;
+        }
+        self.min = if self.count == 0 {
+            other.min
+        } else {
+            self.min.min(other.min)
+        };
+        self.max = self.max.max(other.max);
+        self.zeros += other.zeros;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_squares += other.sum_squares;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Estimated value at quantile `q` (0.0 to 1.0), clamped to the exact
+    /// min and max.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).round() as u64;
+        if rank < self.zeros {
+            return Some(0.0);
+        }
+
+        let mut seen = self.zeros;
+        for (bin, count) in &self.bins {
+            seen += count;
+            if seen > rank {
+                // Midpoint of the bin in the relative-error sense.
+                let value = 2.0 * gamma().powi(*bin) / (gamma() + 1.0);
+                return Some(value.clamp(self.min as f64, self.max as f64));
+            }
+        }
+        Some(self.max as f64)
+    }
+
+    pub fn summary(&self) -> Option<LatencySummary> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let count = self.count as f64;
+        let mean = self.sum / count;
+        let variance = (self.sum_squares / count - mean * mean).max(0.0);
+
+        Some(LatencySummary {
+            min_ms: self.min,
+            max_ms: self.max,
+            mean_ms: round2(mean),
+            stddev_ms: round2(variance.sqrt()),
+            p50_ms: round2(self.quantile(0.50)?),
+            p90_ms: round2(self.quantile(0.90)?),
+            p95_ms: round2(self.quantile(0.95)?),
+            p99_ms: round2(self.quantile(0.99)?),
+        })
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Response time sketches of one check, so any window up to `retention` can
+/// be summarised by merging the buckets it covers. The last hour is kept in
+/// one-minute buckets, which are folded into hourly buckets as they age, so
+/// short windows stay precise without keeping a sketch per minute for days.
+pub struct LatencyTracker {
+    minutes: VecDeque<(i64, LatencySketch)>,
+    hours: VecDeque<(i64, LatencySketch)>,
+    retention: Duration,
+}
+
+impl LatencyTracker {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            minutes: VecDeque::new(),
+            hours: VecDeque::new(),
+            retention,
+        }
+    }
+
+    pub fn record(&mut self, at: DateTime<Utc>, ms: u64) {
+        let minute = at.timestamp_millis() / MINUTE_MS;
+        match self.minutes.back_mut() {
+            Some((last, sketch)) if *last >= minute => sketch.add(ms),
+            _ => {
+                let mut sketch = LatencySketch::default();
+                sketch.add(ms);
+                self.minutes.push_back((minute, sketch));
+            }
+        }
+
+        let fold_before = (at.timestamp_millis() - HOUR_MS) / MINUTE_MS;
+        while self
+            .minutes
+            .front()
+            .is_some_and(|(minute, _)| *minute < fold_before)
+        {
+            let Some((minute, sketch)) = self.minutes.pop_front() else {
+                break;
+            };
+            let hour = minute * MINUTE_MS / HOUR_MS;
+            match self.hours.back_mut() {
+                Some((last, merged)) if *last >= hour => merged.merge(&sketch),
+                _ => self.hours.push_back((hour, sketch)),
+            }
+        }
+
+        let oldest = (at - self.retention).timestamp_millis() / HOUR_MS;
+        while self.hours.front().is_some_and(|(hour, _)| *hour < oldest) {
+            self.hours.pop_front();
+        }
+    }
+
+    /// All response times recorded within `window` before `now`. Windows that
+    /// start within the last hour have one-minute resolution at their start,
+    /// longer ones hourly.
+    pub fn sketch(&self, window: Duration, now: DateTime<Utc>) -> LatencySketch {
+        let start = (now - window).timestamp_millis();
+        let first_minute = start / MINUTE_MS;
+        let within_minutes = self
+            .minutes
+            .front()
+            .is_some_and(|(minute, _)| *minute <= first_minute);
+
+        let mut merged = LatencySketch::default();
+        if !within_minutes {
+            let first_hour = start / HOUR_MS;
+            for (_, sketch) in self.hours.iter().filter(|(hour, _)| *hour >= first_hour) {
+                merged.merge(sketch);
+            }
+        }
+        for (_, sketch) in self
+            .minutes
+            .iter()
+            .filter(|(minute, _)| *minute >= first_minute)
+        {
+            merged.merge(sketch);
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sketch_of(values: impl IntoIterator<Item = u64>) -> LatencySketch {
+        let mut sketch = LatencySketch::default();
+        for ms in values {
+            sketch.add(ms);
+        }
+        sketch
+    }
+
+    fn assert_close(estimate: f64, exact: f64) {
+        assert!(
+            (estimate - exact).abs() <= exact * ALPHA,
+            "{} not within 2% of {}",
+            estimate,
+            exact
+        );
+    }
+
+    #[test]
+    fn quantiles_are_within_alpha() {
+        let sketch = sketch_of(1..=10_000);
+        assert_close(sketch.quantile(0.5).unwrap(), 5_000.0);
+        assert_close(sketch.quantile(0.9).unwrap(), 9_000.0);
+        assert_close(sketch.quantile(0.99).unwrap(), 9_900.0);
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(1.0), Some(10_000.0));
+        assert_eq!(LatencySketch::default().quantile(0.5), None);
+    }
+
+    #[test]
+    fn zeros_and_exact_extremes() {
+        let sketch = sketch_of([0, 0, 0, 40, 50]);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        let summary = sketch.summary().unwrap();
+        assert_eq!((summary.min_ms, summary.max_ms), (0, 50));
+        assert_eq!(summary.mean_ms, 18.0);
+        assert_close(summary.p99_ms, 50.0);
+        assert!(LatencySketch::default().summary().is_none());
+    }
+
+    #[test]
+    fn merge_matches_single_sketch() {
+        let mut merged = sketch_of(1..=500);
+        merged.merge(&sketch_of(501..=1_000));
+        merged.merge(&LatencySketch::default());
+        let whole = sketch_of(1..=1_000);
+
+        assert_eq!(merged.count(), 1_000);
+        assert_eq!(merged.bins, whole.bins);
+        let (a, b) = (merged.summary().unwrap(), whole.summary().unwrap());
+        assert_eq!(
+            (a.min_ms, a.max_ms, a.p95_ms),
+            (b.min_ms, b.max_ms, b.p95_ms)
+        );
+        assert_eq!(a.stddev_ms, b.stddev_ms);
+
+        let mut empty = LatencySketch::default();
+        empty.merge(&sketch_of([7]));
+        assert_eq!((empty.min, empty.max), (7, 7));
+    }
+
+    #[test]
+    fn short_windows_have_minute_resolution() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut tracker = LatencyTracker::new(Duration::days(1));
+        // One sample per minute for three hours, valued by its minute.
+        for minute in 0..180 {
+            tracker.record(start + Duration::minutes(minute), minute as u64);
+        }
+        let now = start + Duration::minutes(179);
+
+        let quarter = tracker.sketch(Duration::minutes(15), now);
+        assert_eq!(quarter.count(), 16);
+        assert_eq!(quarter.min, 164);
+
+        let hour = tracker.sketch(Duration::hours(1), now);
+        assert_eq!(hour.count(), 61);
+        assert_eq!(hour.min, 119);
+
+        // Older samples are folded per hour, so the start rounds down.
+        let two_hours = tracker.sketch(Duration::minutes(90), now);
+        assert_eq!(two_hours.min, 60);
+        assert_eq!(tracker.sketch(Duration::days(1), now).count(), 180);
+        assert!(tracker.minutes.len() <= 61);
+        assert_eq!(tracker.hours.len(), 2);
+    }
+
+    #[test]
+    fn drops_buckets_past_retention() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut tracker = LatencyTracker::new(Duration::hours(3));
+        for hour in 0..10 {
+            tracker.record(start + Duration::hours(hour), 10);
+        }
+        let now = start + Duration::hours(9);
+        assert_eq!(tracker.sketch(Duration::days(1), now).count(), 4);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod flapping;
+pub mod latency;
+pub mod uptime;
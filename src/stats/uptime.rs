@@ -0,0 +1,233 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+
+const BUCKET_MS: i64 = 3_600_000;
+
+/// Time-weighted uptime of one check over rolling windows.
+///
+/// Each result is assumed to hold until the next one, capped at `max_gap` so
+/// periods where the monitor was not running are not counted at all. Observed
+/// time is accumulated in hourly buckets, so windows have hourly resolution at
+/// their start and memory stays bounded by `retention`.
+pub struct UptimeTracker {
+    buckets: VecDeque<Bucket>,
+    last: Option<(DateTime<Utc>, bool)>,
+    max_gap: Duration,
+    retention: Duration,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Bucket {
+    pub hour: i64,
+    pub up_ms: i64,
+    pub total_ms: i64,
+}
+
+/// Observed and working time within a window, in milliseconds.
+#[derive(Clone, Copy, Default)]
+pub struct UptimeSample {
+    pub up_ms: i64,
+    pub total_ms: i64,
+}
+
+impl UptimeSample {
+    pub fn add(&mut self, other: UptimeSample) {
+        self.up_ms += other.up_ms;
+        self.total_ms += other.total_ms;
+    }
+
+    pub fn percentage(&self) -> Option<f64> {
+        (self.total_ms > 0).then(|| self.up_ms as f64 / self.total_ms as f64 * 100.0)
+    }
+}
+
+impl UptimeTracker {
+    pub fn new(max_gap: Duration, retention: Duration) -> Self {
+        Self {
+            buckets: VecDeque::new(),
+            last: None,
+            max_gap,
+            retention,
+        }
+    }
+
+    /// Rebuilds a tracker from persisted hourly buckets (oldest first) and the
+    /// latest result.
+    pub fn restore(
+        max_gap: Duration,
+        retention: Duration,
+        buckets: Vec<Bucket>,
+        last: Option<(DateTime<Utc>, bool)>,
+    ) -> Self {
+        Self {
+            buckets: buckets.into(),
+            last,
+            max_gap,
+            retention,
+        }
+    }
+
+    pub fn record(&mut self, at: DateTime<Utc>, success: bool) {
+        if let Some((since, was_up)) = self.last {
+            self.add_span(since, at, was_up);
+        }
+        self.last = Some((at, success));
+
+        let oldest = (at - self.retention).timestamp_millis() / BUCKET_MS;
+        while self.buckets.front().is_some_and(|b| b.hour < oldest) {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Counts the latest result until `at`, then stops counting until the next
+    /// result, e.g. during maintenance.
+    pub fn pause(&mut self, at: DateTime<Utc>) {
+        if let Some((since, was_up)) = self.last.take() {
+            self.add_span(since, at, was_up);
+        }
+    }
+
+    /// Time observed within `window` before `now`, including the still-open
+    /// span since the latest result.
+    pub fn sample(&self, window: Duration, now: DateTime<Utc>) -> UptimeSample {
+        let first_hour = (now - window).timestamp_millis() / BUCKET_MS;
+        let mut sample = UptimeSample::default();
+
+        for bucket in self.buckets.iter().filter(|b| b.hour >= first_hour) {
+            sample.up_ms += bucket.up_ms;
+            sample.total_ms += bucket.total_ms;
+        }
+
+        if let Some((since, was_up)) = self.last {
+            let open_ms = (now - since).min(self.max_gap).num_milliseconds().max(0);
+            sample.total_ms += open_ms;
+            if was_up {
+                sample.up_ms += open_ms;
+            }
+        }
+
+        sample
+    }
+
+    fn add_span(&mut self, from: DateTime<Utc>, to: DateTime<Utc>, was_up: bool) {
+        let mut start = from.timestamp_millis();
+        let end = (from + self.max_gap).min(to).timestamp_millis();
+
+        // Split the span at hour boundaries.
+        while start < end {
+            let hour = start / BUCKET_MS;
+            let chunk_end = end.min((hour + 1) * BUCKET_MS);
+            let ms = chunk_end - start;
+
+            let bucket = match self.buckets.back_mut() {
+                Some(bucket) if bucket.hour == hour => bucket,
+                _ => {
+                    self.buckets.push_back(Bucket {
+                        hour,
+                        ..Bucket::default()
+                    });
+                    self.buckets.back_mut().expect("just pushed")
+                }
+            };
+            bucket.total_ms += ms;
+            if was_up {
+                bucket.up_ms += ms;
+            }
+
+            start = chunk_end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 2, hour, minute, 0).unwrap()
+    }
+
+    fn minutes(sample: UptimeSample) -> (i64, i64) {
+        (sample.up_ms / 60_000, sample.total_ms / 60_000)
+    }
+
+    #[test]
+    fn results_count_for_the_time_they_hold() {
+        let mut tracker = UptimeTracker::new(Duration::hours(1), Duration::days(1));
+        tracker.record(at(10, 0), true);
+        tracker.record(at(10, 30), false);
+        tracker.record(at(10, 40), true);
+
+        // The latest result holds until now.
+        let sample = tracker.sample(Duration::days(1), at(10, 50));
+        assert_eq!(minutes(sample), (40, 50));
+        assert_eq!(sample.percentage(), Some(80.0));
+    }
+
+    #[test]
+    fn gaps_longer_than_max_gap_are_not_counted() {
+        let mut tracker = UptimeTracker::new(Duration::minutes(5), Duration::days(1));
+        tracker.record(at(10, 0), true);
+        tracker.record(at(12, 0), false);
+
+        assert_eq!(
+            minutes(tracker.sample(Duration::days(1), at(13, 0))),
+            (5, 10)
+        );
+    }
+
+    #[test]
+    fn windows_start_at_the_hour() {
+        let mut tracker = UptimeTracker::new(Duration::hours(2), Duration::days(1));
+        tracker.record(at(10, 30), true);
+        tracker.record(at(11, 30), false);
+
+        // The 10:30-11:30 span is split between the 10:00 and 11:00 buckets.
+        assert_eq!(
+            minutes(tracker.sample(Duration::hours(2), at(12, 0))),
+            (60, 90)
+        );
+        assert_eq!(
+            minutes(tracker.sample(Duration::hours(1), at(12, 0))),
+            (30, 60)
+        );
+        // Within the 11:00 bucket the window covers the whole hour.
+        assert_eq!(
+            minutes(tracker.sample(Duration::hours(1), at(12, 45))),
+            (30, 105)
+        );
+    }
+
+    #[test]
+    fn pause_stops_counting_until_the_next_result() {
+        let mut tracker = UptimeTracker::new(Duration::hours(1), Duration::days(1));
+        tracker.record(at(10, 0), true);
+        tracker.pause(at(10, 10));
+        assert_eq!(
+            minutes(tracker.sample(Duration::days(1), at(10, 30))),
+            (10, 10)
+        );
+
+        tracker.record(at(10, 40), false);
+        assert_eq!(
+            minutes(tracker.sample(Duration::days(1), at(10, 50))),
+            (10, 20)
+        );
+    }
+
+    #[test]
+    fn buckets_older_than_retention_are_dropped() {
+        let mut tracker = UptimeTracker::new(Duration::hours(1), Duration::hours(2));
+        tracker.record(at(8, 0), false);
+        tracker.record(at(8, 30), true);
+        tracker.record(at(11, 0), true);
+
+        // Only the 9:00 bucket is within two hours; the 8:30 result held
+        // for one `max_gap` across 9:00.
+        assert_eq!(
+            minutes(tracker.sample(Duration::days(1), at(11, 0))),
+            (30, 30)
+        );
+    }
+}
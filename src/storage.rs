@@ -0,0 +1,670 @@
+use crate::models::incident::Incident;
+use crate::models::maintenance::MaintenanceWindow;
+use crate::state::{CheckRecord, HealthStatus};
+use crate::stats::uptime::Bucket;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Embedded SQLite store for check results, incidents and maintenance
+/// windows created through the API.
+///
+/// Every result is appended to `check_results` (pruned after the retention
+/// period) and counted in `check_totals`, so lifetime counters survive both
+/// restarts and pruning. Writes go through a background thread so recording a
+/// result never blocks the async runtime.
+#[derive(Clone)]
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+    writer: Sender<PendingWrite>,
+}
+
+enum PendingWrite {
+    Result {
+        service: String,
+        check_type: String,
+        record: CheckRecord,
+    },
+    Incident(Incident),
+    Maintenance {
+        window: MaintenanceWindow,
+        ends_at: Option<DateTime<Utc>>,
+    },
+    DeleteMaintenance(u64),
+}
+
+/// Persisted state of one check, used to restore `AppState` at startup.
+pub struct StoredCheck {
+    pub service: String,
+    pub check_type: String,
+    pub total_checks: u64,
+    pub successful_checks: u64,
+    pub last: Option<CheckRecord>,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS check_results (
+        id INTEGER PRIMARY KEY,
+        service TEXT NOT NULL,
+        check_type TEXT NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        response_time_ms INTEGER,
+        message TEXT NOT NULL,
+        maintenance INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS check_results_by_check
+        ON check_results (service, check_type, timestamp_ms);
+    CREATE TABLE IF NOT EXISTS check_totals (
+        service TEXT NOT NULL,
+        check_type TEXT NOT NULL,
+        total_checks INTEGER NOT NULL,
+        successful_checks INTEGER NOT NULL,
+        PRIMARY KEY (service, check_type)
+    );
+    CREATE TABLE IF NOT EXISTS incidents (
+        id INTEGER PRIMARY KEY,
+        service TEXT NOT NULL,
+        started_at_ms INTEGER NOT NULL,
+        ended_at_ms INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS maintenance_windows (
+        id INTEGER PRIMARY KEY,
+        ends_at_ms INTEGER,
+        data TEXT NOT NULL
+    );
+";
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>, retention_days: u32) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        conn.execute_batch(SCHEMA)?;
+
+        // Added after the first release.
+        let has_maintenance = conn
+            .prepare("SELECT 1 FROM pragma_table_info('check_results') WHERE name = 'maintenance'")?
+            .exists([])?;
+        if !has_maintenance {
+            conn.execute_batch(
+                "ALTER TABLE check_results ADD COLUMN maintenance INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+
+        let conn = Arc::new(Mutex::new(conn));
+        let (writer, receiver) = mpsc::channel();
+
+        let writer_conn = conn.clone();
+        thread::spawn(move || write_loop(writer_conn, receiver, retention_days));
+
+        Ok(Self { conn, writer })
+    }
+
+    /// Queues a check result for writing.
+    pub fn record(&self, service: &str, check_type: &str, record: &CheckRecord) {
+        let pending = PendingWrite::Result {
+            service: service.to_string(),
+            check_type: check_type.to_string(),
+            record: record.clone(),
+        };
+        if self.writer.send(pending).is_err() {
+            println!("History writer stopped, dropping result for {}", service);
+        }
+    }
+
+    /// Queues an opened, updated or closed incident for writing.
+    pub fn record_incident(&self, incident: &Incident) {
+        if self
+            .writer
+            .send(PendingWrite::Incident(incident.clone()))
+            .is_err()
+        {
+            println!(
+                "History writer stopped, dropping incident {} for {}",
+                incident.id, incident.service_name
+            );
+        }
+    }
+
+    /// Queues a maintenance window created through the API for writing;
+    /// `ends_at` is when it can be pruned.
+    pub fn record_maintenance(&self, window: &MaintenanceWindow, ends_at: Option<DateTime<Utc>>) {
+        let pending = PendingWrite::Maintenance {
+            window: window.clone(),
+            ends_at,
+        };
+        if self.writer.send(pending).is_err() {
+            println!("History writer stopped, dropping maintenance window");
+        }
+    }
+
+    pub fn delete_maintenance(&self, id: u64) {
+        if self
+            .writer
+            .send(PendingWrite::DeleteMaintenance(id))
+            .is_err()
+        {
+            println!("History writer stopped, keeping maintenance window {}", id);
+        }
+    }
+
+    /// Maintenance windows created through the API, oldest first.
+    pub fn load_maintenance(&self) -> anyhow::Result<Vec<MaintenanceWindow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare("SELECT data FROM maintenance_windows ORDER BY id")?;
+
+        let mut windows = Vec::new();
+        for data in query.query_map([], |row| row.get::<_, String>(0))? {
+            windows.push(serde_json::from_str(&data?)?);
+        }
+        Ok(windows)
+    }
+
+    /// Incidents that are still open or ended after `since`, oldest first,
+    /// and the highest incident id ever stored.
+    pub fn load_incidents(&self, since: DateTime<Utc>) -> anyhow::Result<(Vec<Incident>, u64)> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare(
+            "SELECT data FROM incidents
+             WHERE ended_at_ms IS NULL OR ended_at_ms >= ?1
+             ORDER BY id",
+        )?;
+
+        let mut incidents = Vec::new();
+        for data in query.query_map(params![since.timestamp_millis()], |row| {
+            row.get::<_, String>(0)
+        })? {
+            incidents.push(serde_json::from_str(&data?)?);
+        }
+
+        let last_id: Option<i64> =
+            conn.query_row("SELECT MAX(id) FROM incidents", [], |row| row.get(0))?;
+
+        Ok((incidents, last_id.unwrap_or(0) as u64))
+    }
+
+    /// Loads lifetime counters and the latest result of every stored check.
+    pub fn load(&self) -> anyhow::Result<Vec<StoredCheck>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut totals = conn.prepare(
+            "SELECT service, check_type, total_checks, successful_checks FROM check_totals",
+        )?;
+        let mut last = conn.prepare(
+            "SELECT timestamp_ms, status, response_time_ms, message, maintenance FROM check_results
+             WHERE service = ?1 AND check_type = ?2
+             ORDER BY timestamp_ms DESC LIMIT 1",
+        )?;
+
+        let rows = totals.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+
+        let mut checks = Vec::new();
+        for row in rows {
+            let (service, check_type, total, successful) = row?;
+            let last = last
+                .query_row(params![service, check_type], read_record)
+                .optional()?;
+            checks.push(StoredCheck {
+                service,
+                check_type,
+                total_checks: total as u64,
+                successful_checks: successful as u64,
+                last,
+            });
+        }
+
+        Ok(checks)
+    }
+
+    /// Stored results of one check between `since` and `until`, oldest first.
+    ///
+    /// At most `limit` results are returned; when more match, the most recent
+    /// ones are kept.
+    pub fn history(
+        &self,
+        service: &str,
+        check_type: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<CheckRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare_cached(
+            "SELECT timestamp_ms, status, response_time_ms, message, maintenance FROM check_results
+             WHERE service = ?1 AND check_type = ?2
+               AND timestamp_ms >= ?3 AND timestamp_ms <= ?4
+             ORDER BY timestamp_ms DESC LIMIT ?5",
+        )?;
+
+        let since = since.map_or(i64::MIN, |t| t.timestamp_millis());
+        let until = until.map_or(i64::MAX, |t| t.timestamp_millis());
+        let mut records = query
+            .query_map(
+                params![service, check_type, since, until, limit as i64],
+                read_record,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        records.reverse();
+        Ok(records)
+    }
+
+    /// Measured response times of one check since `since`, oldest first.
+    pub fn response_times(
+        &self,
+        service: &str,
+        check_type: &str,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<(DateTime<Utc>, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare_cached(
+            "SELECT timestamp_ms, response_time_ms FROM check_results
+             WHERE service = ?1 AND check_type = ?2 AND timestamp_ms >= ?3
+               AND response_time_ms IS NOT NULL
+             ORDER BY timestamp_ms",
+        )?;
+
+        let samples = query
+            .query_map(
+                params![service, check_type, since.timestamp_millis()],
+                |row| {
+                    let timestamp_ms: i64 = row.get(0)?;
+                    let response_time_ms: i64 = row.get(1)?;
+                    Ok((
+                        DateTime::from_timestamp_millis(timestamp_ms).unwrap_or_default(),
+                        response_time_ms as u64,
+                    ))
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(samples)
+    }
+
+    /// Hourly uptime buckets of one check since `since`, oldest first.
+    ///
+    /// Each result counts until the next one, capped at `max_gap`; results
+    /// taken during maintenance do not count. The span after the latest
+    /// result is left to the caller.
+    pub fn uptime_buckets(
+        &self,
+        service: &str,
+        check_type: &str,
+        since: DateTime<Utc>,
+        max_gap: Duration,
+    ) -> anyhow::Result<Vec<Bucket>> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare_cached(
+            "SELECT hour, status, SUM(span_ms) FROM (
+                SELECT timestamp_ms / 3600000 AS hour, status, maintenance,
+                       MIN(COALESCE(LEAD(timestamp_ms) OVER (ORDER BY timestamp_ms), timestamp_ms)
+                           - timestamp_ms, ?4) AS span_ms
+                FROM check_results
+                WHERE service = ?1 AND check_type = ?2 AND timestamp_ms >= ?3
+             )
+             WHERE maintenance = 0
+             GROUP BY hour, status
+             ORDER BY hour",
+        )?;
+
+        let rows = query.query_map(
+            params![
+                service,
+                check_type,
+                since.timestamp_millis(),
+                max_gap.num_milliseconds()
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+
+        let mut buckets: Vec<Bucket> = Vec::new();
+        for row in rows {
+            let (hour, status, span_ms) = row?;
+            if buckets.last().is_none_or(|b| b.hour != hour) {
+                buckets.push(Bucket {
+                    hour,
+                    ..Bucket::default()
+                });
+            }
+            let bucket = buckets.last_mut().expect("just pushed");
+            bucket.total_ms += span_ms;
+            if parse_status(&status).is_success() {
+                bucket.up_ms += span_ms;
+            }
+        }
+
+        Ok(buckets)
+    }
+}
+
+fn read_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<CheckRecord> {
+    let timestamp_ms: i64 = row.get(0)?;
+    let status: String = row.get(1)?;
+    let response_time_ms: Option<i64> = row.get(2)?;
+
+    let status = parse_status(&status);
+
+    Ok(CheckRecord {
+        timestamp: DateTime::from_timestamp_millis(timestamp_ms).unwrap_or_default(),
+        success: status.is_success(),
+        status,
+        response_time_ms: response_time_ms.map(|ms| ms as u64),
+        message: row.get(3)?,
+        maintenance: row.get(4)?,
+    })
+}
+
+fn parse_status(raw: &str) -> HealthStatus {
+    serde_json::from_value(serde_json::Value::String(raw.to_string()))
+        .unwrap_or(HealthStatus::Unknown)
+}
+
+fn format_status(status: &HealthStatus) -> String {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => "Unknown".to_string(),
+    }
+}
+
+fn write_loop(conn: Arc<Mutex<Connection>>, receiver: Receiver<PendingWrite>, retention_days: u32) {
+    let retention = Duration::days(retention_days as i64);
+    let mut last_prune = None;
+
+    while let Ok(first) = receiver.recv() {
+        // Write whatever queued up meanwhile in one transaction.
+        let batch: Vec<PendingWrite> = std::iter::once(first).chain(receiver.try_iter()).collect();
+
+        let mut conn = conn.lock().unwrap();
+        if let Err(e) = write_batch(&mut conn, &batch) {
+            println!("Failed to persist {} history entries: {}", batch.len(), e);
+        }
+
+        let now = Utc::now();
+        if last_prune.is_none_or(|at| now - at > Duration::hours(1)) {
+            last_prune = Some(now);
+            let cutoff = (now - retention).timestamp_millis();
+            if let Err(e) = conn.execute(
+                "DELETE FROM check_results WHERE timestamp_ms < ?1",
+                params![cutoff],
+            ) {
+                println!("Failed to prune check history: {}", e);
+            }
+            if let Err(e) = conn.execute(
+                "DELETE FROM incidents WHERE ended_at_ms < ?1",
+                params![cutoff],
+            ) {
+                println!("Failed to prune incidents: {}", e);
+            }
+            if let Err(e) = conn.execute(
+                "DELETE FROM maintenance_windows WHERE ends_at_ms < ?1",
+                params![cutoff],
+            ) {
+                println!("Failed to prune maintenance windows: {}", e);
+            }
+        }
+    }
+}
+
+fn write_batch(conn: &mut Connection, batch: &[PendingWrite]) -> anyhow::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare_cached(
+            "INSERT INTO check_results
+                (service, check_type, timestamp_ms, status, response_time_ms, message, maintenance)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        let mut count = tx.prepare_cached(
+            "INSERT INTO check_totals (service, check_type, total_checks, successful_checks)
+             VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT (service, check_type) DO UPDATE SET
+                total_checks = total_checks + 1,
+                successful_checks = successful_checks + excluded.successful_checks",
+        )?;
+
+        let mut incident = tx.prepare_cached(
+            "INSERT INTO incidents (id, service, started_at_ms, ended_at_ms, data)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (id) DO UPDATE SET
+                ended_at_ms = excluded.ended_at_ms,
+                data = excluded.data",
+        )?;
+
+        for pending in batch {
+            match pending {
+                PendingWrite::Result {
+                    service,
+                    check_type,
+                    record,
+                } => {
+                    insert.execute(params![
+                        service,
+                        check_type,
+                        record.timestamp.timestamp_millis(),
+                        format_status(&record.status),
+                        record.response_time_ms.map(|ms| ms as i64),
+                        record.message,
+                        record.maintenance,
+                    ])?;
+                    if !record.maintenance {
+                        count.execute(params![
+                            service,
+                            check_type,
+                            record.status.is_success() as i64,
+                        ])?;
+                    }
+                }
+                PendingWrite::Incident(record) => {
+                    incident.execute(params![
+                        record.id as i64,
+                        record.service_name,
+                        record.started_at.timestamp_millis(),
+                        record.ended_at.map(|t| t.timestamp_millis()),
+                        serde_json::to_string(record)?,
+                    ])?;
+                }
+                PendingWrite::Maintenance { window, ends_at } => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO maintenance_windows (id, ends_at_ms, data)
+                         VALUES (?1, ?2, ?3)",
+                        params![
+                            window.id.unwrap_or_default() as i64,
+                            ends_at.map(|t| t.timestamp_millis()),
+                            serde_json::to_string(window)?,
+                        ],
+                    )?;
+                }
+                PendingWrite::DeleteMaintenance(id) => {
+                    tx.execute(
+                        "DELETE FROM maintenance_windows WHERE id = ?1",
+                        params![*id as i64],
+                    )?;
+                }
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "service-monitor-store-{}-{}.db",
+            name,
+            std::process::id()
+        ));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        path
+    }
+
+    fn record(timestamp: DateTime<Utc>, status: HealthStatus) -> CheckRecord {
+        CheckRecord {
+            timestamp,
+            success: status.is_success(),
+            status,
+            response_time_ms: Some(25),
+            message: "HTTP 200".to_string(),
+            maintenance: false,
+        }
+    }
+
+    /// Waits until the writer thread has counted `total` results of the check.
+    fn wait_for_total(store: &Store, check_type: &str, total: u64) -> StoredCheck {
+        for _ in 0..200 {
+            if let Some(stored) = store
+                .load()
+                .unwrap()
+                .into_iter()
+                .find(|s| s.check_type == check_type && s.total_checks >= total)
+            {
+                return stored;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("results were not persisted");
+    }
+
+    #[test]
+    fn counters_and_history_survive_a_restart() {
+        let path = temp_path("restart");
+        let now = Utc::now();
+        {
+            let store = Store::open(&path, 90).unwrap();
+            store.record(
+                "api",
+                "HTTP",
+                &record(now - Duration::minutes(2), HealthStatus::Up),
+            );
+            store.record(
+                "api",
+                "HTTP",
+                &record(now - Duration::minutes(1), HealthStatus::Down),
+            );
+            let mut during_maintenance = record(now, HealthStatus::Down);
+            during_maintenance.maintenance = true;
+            store.record("api", "HTTP", &during_maintenance);
+            store.record("api", "TCP", &record(now, HealthStatus::Up));
+            wait_for_total(&store, "HTTP", 2);
+            wait_for_total(&store, "TCP", 1);
+        }
+
+        let store = Store::open(&path, 90).unwrap();
+        let stored = store.load().unwrap();
+        let http = stored.iter().find(|s| s.check_type == "HTTP").unwrap();
+        assert_eq!(http.service, "api");
+        // Results taken during maintenance are kept but not counted.
+        assert_eq!((http.total_checks, http.successful_checks), (2, 1));
+        let last = http.last.as_ref().unwrap();
+        assert!(last.maintenance);
+        assert_eq!(last.status, HealthStatus::Down);
+
+        let history = store.history("api", "HTTP", None, None, 10).unwrap();
+        let statuses: Vec<_> = history.iter().map(|r| r.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            [HealthStatus::Up, HealthStatus::Down, HealthStatus::Down]
+        );
+        assert_eq!(history[0].response_time_ms, Some(25));
+        assert_eq!(history[0].message, "HTTP 200");
+
+        // The limit keeps the most recent results.
+        let latest = store.history("api", "HTTP", None, None, 1).unwrap();
+        assert_eq!(latest.len(), 1);
+        assert!(latest[0].maintenance);
+    }
+
+    #[test]
+    fn prunes_results_older_than_retention_but_keeps_counters() {
+        let path = temp_path("prune");
+        let now = Utc::now();
+        let store = Store::open(&path, 1).unwrap();
+        // The first write is followed by a prune.
+        store.record(
+            "api",
+            "HTTP",
+            &record(now - Duration::days(2), HealthStatus::Up),
+        );
+        store.record("api", "HTTP", &record(now, HealthStatus::Up));
+        let stored = wait_for_total(&store, "HTTP", 2);
+        assert_eq!(stored.total_checks, 2);
+
+        let history = store.history("api", "HTTP", None, None, 10).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            history[0].timestamp.timestamp_millis(),
+            now.timestamp_millis()
+        );
+    }
+
+    #[test]
+    fn uptime_buckets_weight_results_by_time() {
+        let path = temp_path("buckets");
+        let hour = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let store = Store::open(&path, 36500).unwrap();
+        store.record("api", "HTTP", &record(hour, HealthStatus::Up));
+        store.record(
+            "api",
+            "HTTP",
+            &record(hour + Duration::minutes(30), HealthStatus::Down),
+        );
+        // Down for 90 minutes, of which only `max_gap` counts.
+        store.record(
+            "api",
+            "HTTP",
+            &record(hour + Duration::minutes(120), HealthStatus::Up),
+        );
+        wait_for_total(&store, "HTTP", 3);
+
+        let buckets = store
+            .uptime_buckets("api", "HTTP", hour, Duration::hours(1))
+            .unwrap();
+        let hours: Vec<(i64, i64, i64)> = buckets
+            .iter()
+            .map(|b| {
+                (
+                    b.hour - buckets[0].hour,
+                    b.up_ms / 60_000,
+                    b.total_ms / 60_000,
+                )
+            })
+            .collect();
+        // Each span is counted in the hour it starts; the latest result has none yet.
+        assert_eq!(hours, [(0, 30, 90), (2, 0, 0)]);
+        assert_eq!(buckets[0].hour, hour.timestamp_millis() / 3_600_000);
+
+        // Only results since `since` count.
+        let later = store
+            .uptime_buckets(
+                "api",
+                "HTTP",
+                hour + Duration::minutes(30),
+                Duration::hours(1),
+            )
+            .unwrap();
+        assert_eq!(later[0].up_ms, 0);
+        assert_eq!(later[0].total_ms, 3_600_000);
+    }
+}
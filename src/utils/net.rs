@@ -68,3 +68,43 @@ pub fn normalize_host(raw: &str) -> String {
 
     s
 }
+
+/// Matches a certificate name (SAN or CN) against a hostname.
+///
+/// - Comparison is case-insensitive and ignores a trailing `.`
+/// - A leading `*.` wildcard matches exactly one label (`*.example.com` matches
+///   `www.example.com` but not `example.com` or `a.b.example.com`)
+pub fn hostname_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => match host.split_once('.') {
+            Some((label, rest)) => !label.is_empty() && rest == suffix,
+            None => false,
+        },
+        None => pattern == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_names_ignore_case_and_trailing_dot() {
+        assert!(hostname_matches("Example.COM", "example.com."));
+        assert!(hostname_matches("example.com.", "EXAMPLE.com"));
+        assert!(!hostname_matches("example.com", "www.example.com"));
+    }
+
+    #[test]
+    fn wildcards_match_exactly_one_label() {
+        assert!(hostname_matches("*.example.com", "www.example.com"));
+        assert!(hostname_matches("*.example.com", "API.example.com."));
+        assert!(!hostname_matches("*.example.com", "example.com"));
+        assert!(!hostname_matches("*.example.com", "a.b.example.com"));
+        assert!(!hostname_matches("*.example.com", ".example.com"));
+        assert!(!hostname_matches("*.example.com", "www.example.org"));
+    }
+}